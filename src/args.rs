@@ -1,10 +1,134 @@
-use std::u16;
+use std::{fmt::Display, fs, time::Duration};
+
+use crate::{
+    cidr::Cidr,
+    config::{self, Value},
+    http::StatusCode,
+    sha256::{constant_time_eq, salted_sha256_hex, sha256_hex},
+};
+
+/// Config file keys and the flags they stand in for.
+const CONFIG_KEYS: [(&str, &str); 47] = [
+    ("user", "--user"),
+    ("credentials_file", "--credentials-file"),
+    ("hash_scheme", "--hash-scheme"),
+    ("allow_client", "--allow-client"),
+    ("host", "--host"),
+    ("port", "--port"),
+    ("listen_fd", "--listen-fd"),
+    ("user_switch", "--user-switch"),
+    ("protocol", "--protocol"),
+    ("strict", "--strict"),
+    ("log_line", "--log-line"),
+    ("json_logs", "--json-logs"),
+    ("log_sample", "--log-sample"),
+    ("coalesce", "--coalesce"),
+    ("canonical_headers", "--canonical-headers"),
+    ("whoami", "--whoami"),
+    ("landing", "--landing"),
+    ("cors", "--cors"),
+    ("add_date", "--add-date"),
+    ("trace_raw", "--trace-raw"),
+    ("forwarded_header", "--forwarded-header"),
+    ("warmup_time", "--warmup-time"),
+    ("max_lifetime", "--max-lifetime"),
+    ("max_request_body_time", "--max-request-body-time"),
+    ("slow_connect_threshold", "--slow-connect-threshold"),
+    ("tcp_keepalive", "--tcp-keepalive"),
+    ("drain_signal", "--drain-signal"),
+    ("follow_redirects", "--follow-redirects"),
+    ("max_request_bytes", "--max-request-bytes"),
+    ("max_response_bytes", "--max-response-bytes"),
+    ("max_forward_headers", "--max-forward-headers"),
+    ("peek_bytes", "--peek-bytes"),
+    ("log_sni", "--log-sni"),
+    ("connect_message", "--connect-message"),
+    ("fallback_upstream", "--fallback-upstream"),
+    ("upstream_retries", "--upstream-retries"),
+    ("breaker_threshold", "--breaker-threshold"),
+    ("breaker_window", "--breaker-window"),
+    ("breaker_cooldown", "--breaker-cooldown"),
+    ("request_id_header", "--request-id-header"),
+    ("block_status", "--block-status"),
+    ("block_status_as", "--block-status-as"),
+    ("map_status", "--map-status"),
+    ("echo_upstream_errors", "--echo-upstream-errors"),
+    ("drop_header", "--drop-header"),
+    ("deny_user_agent", "--deny-user-agent"),
+    ("redact_header", "--redact-header"),
+];
+
+const MAX_RESPONSE_FILE_DEPTH: usize = 8;
+
+const REDACTED_HEADERS: [&str; 4] = [
+    "Authorization",
+    "Proxy-Authorization",
+    "Cookie",
+    "Set-Cookie",
+];
 
 #[derive(Debug)]
 pub struct Args {
     pub user: Option<String>,
+    pub credentials_file: Option<String>,
+    /// `(username, password)` pairs from `--credentials-file`, with the
+    /// password stored as `hash_scheme` says.
+    pub credentials: Vec<(String, String)>,
+    pub hash_scheme: HashScheme,
+    pub allow_clients: Vec<Cidr>,
+    /// The address to listen on: an IP (IPv6 without brackets) or hostname.
+    pub host: String,
     pub port: u16,
+    pub listen_fd: Option<i32>,
+    /// The `(uid, gid)` to drop to once the listener is bound.
+    pub user_switch: Option<(u32, u32)>,
     pub protocol: Protocol,
+    pub drop_headers: Vec<String>,
+    pub deny_user_agents: Vec<String>,
+    pub redact_headers: Vec<String>,
+    pub strict: bool,
+    /// How long after starting to answer every request with a 503.
+    pub warmup_time: Option<Duration>,
+    pub max_lifetime: Option<Duration>,
+    pub max_request_body_time: Option<Duration>,
+    pub slow_connect_threshold: Option<Duration>,
+    pub tcp_keepalive: Option<Duration>,
+    pub drain_signal: DrainSignal,
+    pub peek_bytes: usize,
+    pub log_sni: bool,
+    pub connect_message: String,
+    /// A proxy to CONNECT through when a tunnel's target can't be reached.
+    pub fallback_upstream: Option<String>,
+    /// How many more times to try CONNECTing through it when it fails in a
+    /// way that may pass.
+    pub upstream_retries: usize,
+    /// Consecutive connect failures before an upstream's circuit opens.
+    pub breaker_threshold: Option<usize>,
+    pub breaker_window: Duration,
+    pub breaker_cooldown: Duration,
+    pub request_id_header: String,
+    pub log_line: bool,
+    pub json_logs: bool,
+    pub log_sample: f64,
+    pub coalesce: bool,
+    pub canonical_headers: bool,
+    pub whoami: bool,
+    pub landing: bool,
+    pub cors: bool,
+    pub add_date: bool,
+    pub trace_raw: bool,
+    pub forwarded_header: Option<ForwardedHeader>,
+    pub follow_redirects: usize,
+    pub max_request_bytes: Option<usize>,
+    pub max_response_bytes: Option<usize>,
+    pub max_forward_headers: Option<usize>,
+    pub block_statuses: Vec<u16>,
+    pub block_status_as: StatusCode,
+    /// Upstream statuses to relay as another, as `(from, to)`.
+    pub map_statuses: Vec<(u16, StatusCode)>,
+    /// Whether a 5xx masked by `--block-status` keeps the upstream's body.
+    pub echo_upstream_errors: bool,
+    pub print_config: bool,
     pub help: bool,
     pub version: bool,
 }
@@ -12,23 +136,170 @@ pub struct Args {
 impl Args {
     pub fn parse(it: &mut impl Iterator<Item = String>) -> Result<Self, String> {
         let mut user = None;
+        let mut credentials_file = None;
+        let mut credentials = Vec::new();
+        let mut hash_scheme = HashScheme::Plain;
+        let mut allow_clients = Vec::new();
+        let mut host = String::from("localhost");
         let mut port = 8080;
+        let mut listen_fd = None;
+        let mut user_switch = None;
         let mut protocol = Protocol::HTTP;
+        let mut drop_headers = Vec::new();
+        let mut deny_user_agents = Vec::new();
+        let mut redact_headers: Vec<String> = REDACTED_HEADERS.map(String::from).to_vec();
+        let mut strict = false;
+        let mut warmup_time = None;
+        let mut max_lifetime = None;
+        let mut max_request_body_time = None;
+        let mut slow_connect_threshold = None;
+        let mut tcp_keepalive = None;
+        let mut drain_signal = DrainSignal::Usr1;
+        let mut peek_bytes = 0;
+        let mut log_sni = false;
+        let mut connect_message = String::from("Connection Established");
+        let mut fallback_upstream = None;
+        let mut upstream_retries = 0;
+        let mut breaker_threshold = None;
+        let mut breaker_window = Duration::from_secs(10);
+        let mut breaker_cooldown = Duration::from_secs(30);
+        let mut request_id_header = String::from("X-Request-Id");
+        let mut log_line = false;
+        let mut json_logs = false;
+        let mut log_sample = 1.0;
+        let mut coalesce = false;
+        let mut canonical_headers = false;
+        let mut whoami = false;
+        let mut landing = false;
+        let mut cors = false;
+        let mut add_date = false;
+        let mut trace_raw = false;
+        let mut forwarded_header = None;
+        let mut follow_redirects = 0;
+        let mut max_request_bytes = None;
+        let mut max_response_bytes = None;
+        let mut max_forward_headers = None;
+        let mut block_statuses = Vec::new();
+        let mut block_status_as = StatusCode::BadGateway;
+        let mut map_statuses = Vec::new();
+        let mut echo_upstream_errors = false;
+        let mut print_config = false;
         let mut help = false;
         let mut version = false;
 
         it.next(); // "rox"
+        let cli = expand_response_files(it, 0)?;
+
+        // Config file values come first so that explicit flags override them
+        // Found before `--config=path` is split up like the other flags
+        let config_path =
+            cli.iter()
+                .enumerate()
+                .find_map(|(i, a)| match a.strip_prefix("--config") {
+                    Some("") => Some(cli.get(i + 1).map(String::as_str)),
+                    Some(rest) => rest.strip_prefix('=').map(Some),
+                    None => None,
+                });
+
+        let mut args = match config_path {
+            Some(path) => {
+                let path = path.ok_or("🚨 Error: no config provided 🚨")?;
+                config_args(config::load(path)?)?
+            }
+            None => Vec::new(),
+        };
+        args.extend(cli);
+
+        let mut it = args.into_iter();
+
         while let Some(arg) = it.next() {
+            // `--port=9000` reads as `--port 9000`
+            let arg = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with('-') => {
+                    it = std::iter::once(value.to_string())
+                        .chain(it)
+                        .collect::<Vec<_>>()
+                        .into_iter();
+                    flag.to_string()
+                }
+                _ => arg,
+            };
+
             match arg.as_str() {
                 "-h" | "--help" => help = true,
                 "-v" | "--version" => version = true,
-                a if a.starts_with("-p") | a.starts_with("--port") => {
+                "--strict" => strict = true,
+                "--print-config" => print_config = true,
+                "--config" => {
+                    it.next();
+                }
+                "--log-line" => log_line = true,
+                "--json-logs" => json_logs = true,
+                "--log-sample" => {
+                    log_sample = it
+                        .next()
+                        .ok_or("🚨 Error: no ratio provided 🚨")?
+                        .parse()
+                        .map_err(|_| "Error parsing log sample")?;
+
+                    if !(0.0..=1.0).contains(&log_sample) {
+                        return Err(String::from("🚨 Error: log sample must be from 0 to 1 🚨"));
+                    }
+                }
+                "--coalesce" => coalesce = true,
+                "--canonical-headers" => canonical_headers = true,
+                "--whoami" => whoami = true,
+                "--landing" => landing = true,
+                "--cors" => cors = true,
+                "--add-date" => add_date = true,
+                "--trace-raw" => trace_raw = true,
+                "--log-sni" => log_sni = true,
+                "-b" | "--bind" | "--host" => {
+                    let addr = it.next().ok_or("🚨 Error: no bind address provided 🚨")?;
+
+                    // `[::1]`, as IPv6 is written next to a port, binds as `::1`
+                    let addr = match addr.strip_prefix('[').and_then(|a| a.strip_suffix(']')) {
+                        Some(ipv6) => ipv6.to_string(),
+                        None => addr,
+                    };
+
+                    if addr.is_empty() {
+                        return Err(String::from("🚨 Error: bind address is empty 🚨"));
+                    }
+
+                    host = addr;
+                }
+                "-p" | "--port" => {
                     port = it
                         .next()
                         .ok_or("🚨 Error: no port provided 🚨")?
                         .parse()
                         .map_err(|_| "Error parsing port")?;
                 }
+                "--listen-fd" => {
+                    let fd = it
+                        .next()
+                        .ok_or("🚨 Error: no fd provided 🚨")?
+                        .parse()
+                        .map_err(|_| "Error parsing listen fd")?;
+
+                    if fd < 0 {
+                        return Err(String::from("🚨 Error: listen fd must not be negative 🚨"));
+                    }
+
+                    listen_fd = Some(fd);
+                }
+                "--user-switch" => {
+                    let ids = it.next().ok_or("🚨 Error: no uid:gid provided 🚨")?;
+
+                    user_switch = match ids.split_once(':') {
+                        Some((uid, gid)) => Some((
+                            uid.parse().map_err(|_| "Error parsing user switch uid")?,
+                            gid.parse().map_err(|_| "Error parsing user switch gid")?,
+                        )),
+                        None => return Err(format!("🚨 Expected uid:gid, got {} 🚨", ids)),
+                    }
+                }
                 "-P" | "--protocol" => {
                     let proto_str = it.next().ok_or("🚨 Error: no protocol provided 🚨")?;
 
@@ -38,6 +309,203 @@ impl Args {
                     }
                 }
                 "-u" | "--user" => user = Some(it.next().ok_or("🚨 Error: no user provided 🚨")?),
+                "--credentials-file" => {
+                    let path = it
+                        .next()
+                        .ok_or("🚨 Error: no credentials file provided 🚨")?;
+                    credentials.extend(load_credentials(&path)?);
+                    credentials_file = Some(path);
+                }
+                "--hash-scheme" => {
+                    let name = it.next().ok_or("🚨 Error: no hash scheme provided 🚨")?;
+
+                    hash_scheme = match name.to_lowercase().as_str() {
+                        "plain" => HashScheme::Plain,
+                        "sha256" => HashScheme::Sha256,
+                        _ => return Err(format!("🚨 Unknown hash scheme: {} 🚨", name)),
+                    }
+                }
+                "--allow-client" => {
+                    let range = it.next().ok_or("🚨 Error: no client range provided 🚨")?;
+                    allow_clients.push(range.parse()?);
+                }
+                "--tcp-keepalive" => {
+                    let secs = it
+                        .next()
+                        .ok_or("🚨 Error: no keepalive provided 🚨")?
+                        .parse()
+                        .map_err(|_| "Error parsing tcp keepalive")?;
+
+                    tcp_keepalive = Some(Duration::from_secs(secs));
+                }
+                "--drain-signal" => {
+                    let name = it.next().ok_or("🚨 Error: no signal provided 🚨")?;
+
+                    drain_signal = match name.to_uppercase().trim_start_matches("SIG") {
+                        "USR1" => DrainSignal::Usr1,
+                        "USR2" => DrainSignal::Usr2,
+                        "HUP" => DrainSignal::Hup,
+                        _ => return Err(format!("🚨 Unknown drain signal: {} 🚨", name)),
+                    }
+                }
+                "--peek-bytes" => {
+                    peek_bytes = it
+                        .next()
+                        .ok_or("🚨 Error: no byte count provided 🚨")?
+                        .parse()
+                        .map_err(|_| "Error parsing peek bytes")?;
+                }
+                "--connect-message" => {
+                    connect_message = it.next().ok_or("🚨 Error: no message provided 🚨")?
+                }
+                "--fallback-upstream" => {
+                    fallback_upstream = Some(it.next().ok_or("🚨 Error: no upstream provided 🚨")?)
+                }
+                "--upstream-retries" => {
+                    upstream_retries = it
+                        .next()
+                        .ok_or("🚨 Error: no retry count provided 🚨")?
+                        .parse()
+                        .map_err(|_| "Error parsing upstream retries")?;
+                }
+                "--request-id-header" => {
+                    request_id_header = it.next().ok_or("🚨 Error: no header provided 🚨")?
+                }
+                "--redact-header" => {
+                    redact_headers.push(it.next().ok_or("🚨 Error: no header provided 🚨")?)
+                }
+                "--warmup-time" => {
+                    let secs = it
+                        .next()
+                        .ok_or("🚨 Error: no warmup time provided 🚨")?
+                        .parse()
+                        .map_err(|_| "Error parsing warmup time")?;
+
+                    warmup_time = Some(Duration::from_secs(secs));
+                }
+                "--max-lifetime" => {
+                    let secs = it
+                        .next()
+                        .ok_or("🚨 Error: no lifetime provided 🚨")?
+                        .parse()
+                        .map_err(|_| "Error parsing max lifetime")?;
+
+                    max_lifetime = Some(Duration::from_secs(secs));
+                }
+                "--max-request-body-time" => {
+                    let secs = it
+                        .next()
+                        .ok_or("🚨 Error: no body time provided 🚨")?
+                        .parse()
+                        .map_err(|_| "Error parsing max request body time")?;
+
+                    max_request_body_time = Some(Duration::from_secs(secs));
+                }
+                "--breaker-threshold" => {
+                    let count = it
+                        .next()
+                        .ok_or("🚨 Error: no failure count provided 🚨")?
+                        .parse()
+                        .map_err(|_| "Error parsing breaker threshold")?;
+
+                    breaker_threshold = Some(count);
+                }
+                "--breaker-window" => {
+                    let secs = it
+                        .next()
+                        .ok_or("🚨 Error: no window provided 🚨")?
+                        .parse()
+                        .map_err(|_| "Error parsing breaker window")?;
+
+                    breaker_window = Duration::from_secs(secs);
+                }
+                "--breaker-cooldown" => {
+                    let secs = it
+                        .next()
+                        .ok_or("🚨 Error: no cooldown provided 🚨")?
+                        .parse()
+                        .map_err(|_| "Error parsing breaker cooldown")?;
+
+                    breaker_cooldown = Duration::from_secs(secs);
+                }
+                "--slow-connect-threshold" => {
+                    let millis = it
+                        .next()
+                        .ok_or("🚨 Error: no threshold provided 🚨")?
+                        .parse()
+                        .map_err(|_| "Error parsing slow connect threshold")?;
+
+                    slow_connect_threshold = Some(Duration::from_millis(millis));
+                }
+                "--forwarded-header" => {
+                    let mode = it
+                        .next()
+                        .ok_or("🚨 Error: no forwarded header provided 🚨")?;
+
+                    forwarded_header = match mode.to_lowercase().as_str() {
+                        "rfc7239" => Some(ForwardedHeader::Rfc7239),
+                        _ => return Err(format!("🚨 Unknown forwarded header: {} 🚨", mode)),
+                    }
+                }
+                "--follow-redirects" => {
+                    follow_redirects = it
+                        .next()
+                        .ok_or("🚨 Error: no redirect count provided 🚨")?
+                        .parse()
+                        .map_err(|_| "Error parsing follow redirects")?;
+                }
+                "--max-request-bytes" => {
+                    let bytes = it
+                        .next()
+                        .ok_or("🚨 Error: no byte count provided 🚨")?
+                        .parse()
+                        .map_err(|_| "Error parsing max request bytes")?;
+
+                    max_request_bytes = Some(bytes);
+                }
+                "--max-response-bytes" => {
+                    let bytes = it
+                        .next()
+                        .ok_or("🚨 Error: no byte count provided 🚨")?
+                        .parse()
+                        .map_err(|_| "Error parsing max response bytes")?;
+
+                    max_response_bytes = Some(bytes);
+                }
+                "--max-forward-headers" => {
+                    let count = it
+                        .next()
+                        .ok_or("🚨 Error: no header count provided 🚨")?
+                        .parse()
+                        .map_err(|_| "Error parsing max forward headers")?;
+
+                    max_forward_headers = Some(count);
+                }
+                "--block-status" => {
+                    let code = it.next().ok_or("🚨 Error: no status provided 🚨")?;
+                    block_statuses.push(parse_status(&code)? as u16);
+                }
+                "--block-status-as" => {
+                    let code = it.next().ok_or("🚨 Error: no status provided 🚨")?;
+                    block_status_as = parse_status(&code)?;
+                }
+                "--map-status" => {
+                    let mapping = it.next().ok_or("🚨 Error: no status mapping provided 🚨")?;
+
+                    match mapping.split_once('=') {
+                        Some((from, to)) => {
+                            map_statuses.push((parse_status(from)? as u16, parse_status(to)?))
+                        }
+                        None => return Err(format!("🚨 Expected from=to, got {} 🚨", mapping)),
+                    }
+                }
+                "--echo-upstream-errors" => echo_upstream_errors = true,
+                "--drop-header" => {
+                    drop_headers.push(it.next().ok_or("🚨 Error: no header provided 🚨")?)
+                }
+                "--deny-user-agent" => {
+                    deny_user_agents.push(it.next().ok_or("🚨 Error: no user agent provided 🚨")?)
+                }
 
                 _ => return Err(format!("🚨 Invalid argument: {} 🚨", arg)),
             };
@@ -45,19 +513,367 @@ impl Args {
 
         Ok(Self {
             user,
+            credentials_file,
+            credentials,
+            hash_scheme,
+            allow_clients,
+            host,
             port,
+            listen_fd,
+            user_switch,
             protocol,
+            drop_headers,
+            deny_user_agents,
+            redact_headers,
+            strict,
+            warmup_time,
+            max_lifetime,
+            max_request_body_time,
+            slow_connect_threshold,
+            tcp_keepalive,
+            drain_signal,
+            peek_bytes,
+            log_sni,
+            connect_message,
+            fallback_upstream,
+            upstream_retries,
+            breaker_threshold,
+            breaker_window,
+            breaker_cooldown,
+            request_id_header,
+            log_line,
+            json_logs,
+            log_sample,
+            coalesce,
+            canonical_headers,
+            whoami,
+            landing,
+            cors,
+            add_date,
+            trace_raw,
+            forwarded_header,
+            follow_redirects,
+            max_request_bytes,
+            max_response_bytes,
+            max_forward_headers,
+            block_statuses,
+            block_status_as,
+            map_statuses,
+            echo_upstream_errors,
+            print_config,
             help,
             version,
         })
     }
 }
 
+/// Reads `username:password` lines, skipping blanks and `#` comments.
+fn load_credentials(path: &str) -> Result<Vec<(String, String)>, String> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| format!("🚨 Error reading credentials {}: {} 🚨", path, e))?;
+
+    contents
+        .lines()
+        .map(str::trim)
+        .enumerate()
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(i, line)| match line.split_once(':') {
+            Some((name, password)) => Ok((name.to_string(), password.to_string())),
+            None => Err(format!(
+                "🚨 Error parsing credentials {}: line {}: expected username:password 🚨",
+                path,
+                i + 1
+            )),
+        })
+        .collect()
+}
+
+fn parse_status(code: &str) -> Result<StatusCode, String> {
+    match code.parse().map(StatusCode::from_u16) {
+        Ok(StatusCode::Unknown) | Err(_) => Err(format!("🚨 Unknown status code: {} 🚨", code)),
+        Ok(status_code) => Ok(status_code),
+    }
+}
+
+/// Renders the effective configuration in config file syntax, with any
+/// password masked.
+impl Display for Args {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(user) = &self.user {
+            let user = match user.split_once(':') {
+                Some((name, _)) => format!("{}:***", name),
+                None => user.clone(),
+            };
+            writeln!(f, "user = {}", quote(&user))?;
+        }
+
+        if let Some(path) = &self.credentials_file {
+            writeln!(f, "credentials_file = {}", quote(path))?;
+        }
+        writeln!(f, "hash_scheme = {}", quote(&self.hash_scheme.to_string()))?;
+
+        let allow_clients: Vec<_> = self.allow_clients.iter().map(Cidr::to_string).collect();
+        writeln!(f, "allow_client = {}", quote_all(&allow_clients))?;
+        writeln!(f, "host = {}", quote(&self.host))?;
+        writeln!(f, "port = {}", self.port)?;
+        if let Some(fd) = self.listen_fd {
+            writeln!(f, "listen_fd = {}", fd)?;
+        }
+        if let Some((uid, gid)) = self.user_switch {
+            writeln!(f, "user_switch = {}", quote(&format!("{}:{}", uid, gid)))?;
+        }
+        writeln!(f, "protocol = {}", quote(&self.protocol.to_string()))?;
+        writeln!(f, "strict = {}", self.strict)?;
+        writeln!(f, "log_line = {}", self.log_line)?;
+        writeln!(f, "json_logs = {}", self.json_logs)?;
+        writeln!(f, "log_sample = {:?}", self.log_sample)?;
+        writeln!(f, "coalesce = {}", self.coalesce)?;
+        writeln!(f, "canonical_headers = {}", self.canonical_headers)?;
+        writeln!(f, "whoami = {}", self.whoami)?;
+        writeln!(f, "landing = {}", self.landing)?;
+        writeln!(f, "cors = {}", self.cors)?;
+        writeln!(f, "add_date = {}", self.add_date)?;
+        writeln!(f, "trace_raw = {}", self.trace_raw)?;
+
+        if let Some(forwarded_header) = &self.forwarded_header {
+            writeln!(
+                f,
+                "forwarded_header = {}",
+                quote(&forwarded_header.to_string())
+            )?;
+        }
+
+        if let Some(warmup) = self.warmup_time {
+            writeln!(f, "warmup_time = {}", warmup.as_secs())?;
+        }
+
+        if let Some(lifetime) = self.max_lifetime {
+            writeln!(f, "max_lifetime = {}", lifetime.as_secs())?;
+        }
+
+        if let Some(limit) = self.max_request_body_time {
+            writeln!(f, "max_request_body_time = {}", limit.as_secs())?;
+        }
+
+        if let Some(threshold) = self.slow_connect_threshold {
+            writeln!(f, "slow_connect_threshold = {}", threshold.as_millis())?;
+        }
+
+        if let Some(keepalive) = self.tcp_keepalive {
+            writeln!(f, "tcp_keepalive = {}", keepalive.as_secs())?;
+        }
+
+        writeln!(
+            f,
+            "drain_signal = {}",
+            quote(&self.drain_signal.to_string())
+        )?;
+        writeln!(f, "follow_redirects = {}", self.follow_redirects)?;
+        if let Some(bytes) = self.max_request_bytes {
+            writeln!(f, "max_request_bytes = {}", bytes)?;
+        }
+        if let Some(bytes) = self.max_response_bytes {
+            writeln!(f, "max_response_bytes = {}", bytes)?;
+        }
+        if let Some(count) = self.max_forward_headers {
+            writeln!(f, "max_forward_headers = {}", count)?;
+        }
+
+        writeln!(f, "peek_bytes = {}", self.peek_bytes)?;
+        writeln!(f, "log_sni = {}", self.log_sni)?;
+        writeln!(f, "connect_message = {}", quote(&self.connect_message))?;
+        if let Some(fallback) = &self.fallback_upstream {
+            writeln!(f, "fallback_upstream = {}", quote(fallback))?;
+        }
+        writeln!(f, "upstream_retries = {}", self.upstream_retries)?;
+        if let Some(count) = self.breaker_threshold {
+            writeln!(f, "breaker_threshold = {}", count)?;
+        }
+        writeln!(f, "breaker_window = {}", self.breaker_window.as_secs())?;
+        writeln!(f, "breaker_cooldown = {}", self.breaker_cooldown.as_secs())?;
+        writeln!(f, "request_id_header = {}", quote(&self.request_id_header))?;
+        writeln!(f, "block_status = {:?}", self.block_statuses)?;
+        writeln!(f, "block_status_as = {}", self.block_status_as)?;
+        let map_statuses: Vec<_> = self
+            .map_statuses
+            .iter()
+            .map(|(from, to)| format!("{}={}", from, to))
+            .collect();
+        writeln!(f, "map_status = {}", quote_all(&map_statuses))?;
+        writeln!(f, "echo_upstream_errors = {}", self.echo_upstream_errors)?;
+        writeln!(f, "drop_header = {}", quote_all(&self.drop_headers))?;
+        writeln!(f, "deny_user_agent = {}", quote_all(&self.deny_user_agents))?;
+        writeln!(f, "redact_header = {}", quote_all(&self.redact_headers))
+    }
+}
+
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn quote_all(values: &[String]) -> String {
+    let values: Vec<_> = values.iter().map(|v| quote(v)).collect();
+    format!("[{}]", values.join(", "))
+}
+
+/// Turns config file entries into the equivalent command line flags.
+fn config_args(entries: Vec<(String, Value)>) -> Result<Vec<String>, String> {
+    let mut args = Vec::new();
+
+    for (key, value) in entries {
+        let flag = match CONFIG_KEYS.iter().find(|(k, _)| *k == key) {
+            Some((_, flag)) => flag.to_string(),
+            None => return Err(format!("🚨 Unknown config key: {} 🚨", key)),
+        };
+
+        let values = match value {
+            Value::Array(values) => values,
+            value => vec![value],
+        };
+
+        for value in values {
+            match value {
+                Value::Bool(true) => args.push(flag.clone()),
+                Value::Bool(false) => {}
+                Value::String(s) => args.extend([flag.clone(), s]),
+                Value::Integer(n) => args.extend([flag.clone(), n.to_string()]),
+                Value::Float(n) => args.extend([flag.clone(), n.to_string()]),
+                Value::Array(_) => return Err(format!("🚨 Nested array for {} 🚨", key)),
+            }
+        }
+    }
+
+    Ok(args)
+}
+
+/// Splices the contents of `@path` arguments into the argument list,
+/// whitespace-separated, following nested `@path`s up to a fixed depth.
+fn expand_response_files(
+    it: impl Iterator<Item = String>,
+    depth: usize,
+) -> Result<Vec<String>, String> {
+    let mut args = Vec::new();
+
+    for arg in it {
+        let path = match arg.strip_prefix('@') {
+            Some(path) if !path.is_empty() => path,
+            _ => {
+                args.push(arg);
+                continue;
+            }
+        };
+
+        if depth >= MAX_RESPONSE_FILE_DEPTH {
+            return Err(format!("🚨 Too many nested response files: {} 🚨", arg));
+        }
+
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("🚨 Error reading response file {}: {} 🚨", path, e))?;
+
+        let nested = contents.split_whitespace().map(String::from);
+        args.extend(expand_response_files(nested, depth + 1)?);
+    }
+
+    Ok(args)
+}
+
 #[derive(Debug, PartialEq)]
 pub enum Protocol {
     HTTP,
 }
 
+impl Display for Protocol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Protocol::HTTP => "http",
+        };
+
+        write!(f, "{}", s)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ForwardedHeader {
+    Rfc7239,
+}
+
+impl Display for ForwardedHeader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            ForwardedHeader::Rfc7239 => "rfc7239",
+        };
+
+        write!(f, "{}", s)
+    }
+}
+
+/// The signal that makes rox stop accepting connections and exit once the
+/// open ones finish, so a new instance can take over the port.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum DrainSignal {
+    Usr1,
+    Usr2,
+    Hup,
+}
+
+impl Display for DrainSignal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            DrainSignal::Usr1 => "USR1",
+            DrainSignal::Usr2 => "USR2",
+            DrainSignal::Hup => "HUP",
+        };
+
+        write!(f, "{}", s)
+    }
+}
+
+/// How many times a salted `sha256$<salt>$<hex>` password is hashed.
+pub const SALTED_ROUNDS: u32 = 10_000;
+
+/// How passwords in `--credentials-file` are stored.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum HashScheme {
+    Plain,
+    /// Lowercase hex SHA-256 of the password, as `sha256sum` prints it, or
+    /// `sha256$<salt>$<hex>` with the salt and password hashed
+    /// `SALTED_ROUNDS` times
+    Sha256,
+}
+
+impl HashScheme {
+    /// Whether `password` is the one `stored` was made from.
+    pub fn verify(&self, password: &str, stored: &str) -> bool {
+        match self {
+            HashScheme::Plain => constant_time_eq(password.as_bytes(), stored.as_bytes()),
+            HashScheme::Sha256 => {
+                let salted = stored.strip_prefix("sha256$").map(|s| s.split_once('$'));
+                let (digest, stored) = match salted {
+                    Some(Some((salt, stored))) => (
+                        salted_sha256_hex(salt.as_bytes(), password.as_bytes(), SALTED_ROUNDS),
+                        stored,
+                    ),
+                    Some(None) => return false,
+                    None => (sha256_hex(password.as_bytes()), stored),
+                };
+
+                constant_time_eq(digest.as_bytes(), stored.to_ascii_lowercase().as_bytes())
+            }
+        }
+    }
+}
+
+impl Display for HashScheme {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            HashScheme::Plain => "plain",
+            HashScheme::Sha256 => "sha256",
+        };
+
+        write!(f, "{}", s)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -68,7 +884,7 @@ mod test {
 
         let args = Args::parse(&mut it).unwrap();
 
-        assert_eq!(args.help, true);
+        assert!(args.help);
     }
 
     #[test]
@@ -77,7 +893,7 @@ mod test {
 
         let args = Args::parse(&mut it).unwrap();
 
-        assert_eq!(args.help, true);
+        assert!(args.help);
     }
 
     #[test]
@@ -86,7 +902,7 @@ mod test {
 
         let args = Args::parse(&mut it).unwrap();
 
-        assert_eq!(args.version, true);
+        assert!(args.version);
     }
 
     #[test]
@@ -95,7 +911,7 @@ mod test {
 
         let args = Args::parse(&mut it).unwrap();
 
-        assert_eq!(args.version, true);
+        assert!(args.version);
     }
 
     #[test]
@@ -116,6 +932,91 @@ mod test {
         assert_eq!(args.port, 7000);
     }
 
+    #[test]
+    fn it_can_parse_equals_style_values() {
+        for spelling in [
+            &["--port=9000"][..],
+            &["-p=9000"],
+            &["--port", "9000"],
+            &["-p", "9000"],
+        ] {
+            let mut it = ["rox"].iter().chain(spelling).map(|s| s.to_string());
+
+            let args = Args::parse(&mut it).unwrap();
+
+            assert_eq!(args.port, 9000);
+        }
+
+        let mut it = ["rox", "--protocol=http", "--map-status=404=410"]
+            .into_iter()
+            .map(|s| s.to_string());
+
+        let args = Args::parse(&mut it).unwrap();
+
+        assert_eq!(args.protocol, Protocol::HTTP);
+        assert_eq!(args.map_statuses, vec![(404, StatusCode::Gone)]);
+    }
+
+    #[test]
+    fn it_rejects_an_empty_equals_value() {
+        for arg in ["--port=", "-p="] {
+            let mut it = ["rox", arg].into_iter().map(|s| s.to_string());
+
+            assert!(Args::parse(&mut it).is_err());
+        }
+    }
+
+    #[test]
+    fn it_can_parse_bind() {
+        let mut it = ["rox", "--bind", "0.0.0.0"]
+            .into_iter()
+            .map(|s| s.to_string());
+
+        let args = Args::parse(&mut it).unwrap();
+
+        assert_eq!(args.host, "0.0.0.0");
+    }
+
+    #[test]
+    fn it_can_parse_b() {
+        let mut it = ["rox", "-b", "proxy.internal"]
+            .into_iter()
+            .map(|s| s.to_string());
+
+        let args = Args::parse(&mut it).unwrap();
+
+        assert_eq!(args.host, "proxy.internal");
+    }
+
+    #[test]
+    fn it_can_parse_an_ipv6_host() {
+        let mut it = ["rox", "--host", "[::1]"]
+            .into_iter()
+            .map(|s| s.to_string());
+
+        let args = Args::parse(&mut it).unwrap();
+
+        assert_eq!(args.host, "::1");
+    }
+
+    #[test]
+    fn it_defaults_to_localhost() {
+        let mut it = ["rox"].into_iter().map(|s| s.to_string());
+
+        let args = Args::parse(&mut it).unwrap();
+
+        assert_eq!(args.host, "localhost");
+    }
+
+    #[test]
+    fn it_rejects_an_empty_bind_address() {
+        for addr in ["", "[]"] {
+            let mut it = ["rox", "--bind", addr].into_iter().map(|s| s.to_string());
+
+            assert!(Args::parse(&mut it).is_err());
+        }
+    }
+
     #[test]
     fn it_can_prase_protocol() {
         let mut it = ["rox", "--protocol", "HTTP"]
@@ -126,4 +1027,624 @@ mod test {
 
         assert_eq!(args.protocol, Protocol::HTTP);
     }
+
+    #[test]
+    fn it_can_parse_drop_headers() {
+        let mut it = ["rox", "--drop-header", "Cookie", "--drop-header", "Referer"]
+            .into_iter()
+            .map(|s| s.to_string());
+
+        let args = Args::parse(&mut it).unwrap();
+
+        assert_eq!(args.drop_headers, vec!["Cookie", "Referer"]);
+    }
+
+    #[test]
+    fn it_can_parse_redact_headers() {
+        let mut it = ["rox", "--redact-header", "X-Api-Key"]
+            .into_iter()
+            .map(|s| s.to_string());
+
+        let args = Args::parse(&mut it).unwrap();
+
+        assert!(
+            args.redact_headers
+                .contains(&"Proxy-Authorization".to_string())
+        );
+        assert!(args.redact_headers.contains(&"X-Api-Key".to_string()));
+    }
+
+    #[test]
+    fn it_can_parse_peek_bytes() {
+        let mut it = ["rox", "--peek-bytes", "16"]
+            .into_iter()
+            .map(|s| s.to_string());
+
+        let args = Args::parse(&mut it).unwrap();
+
+        assert_eq!(args.peek_bytes, 16);
+    }
+
+    #[test]
+    fn it_can_parse_connect_message() {
+        let mut it = ["rox", "--connect-message", "OK"]
+            .into_iter()
+            .map(|s| s.to_string());
+
+        let args = Args::parse(&mut it).unwrap();
+
+        assert_eq!(args.connect_message, "OK");
+    }
+
+    #[test]
+    fn it_can_parse_log_line() {
+        let mut it = ["rox", "--log-line"].into_iter().map(|s| s.to_string());
+
+        let args = Args::parse(&mut it).unwrap();
+
+        assert!(args.log_line);
+    }
+
+    #[test]
+    fn it_can_parse_a_response_file() {
+        let path = std::env::temp_dir().join(format!("rox-args-{}.txt", std::process::id()));
+        fs::write(&path, "--port 9000\n--strict\n").unwrap();
+
+        let arg = format!("@{}", path.display());
+        let mut it = ["rox", arg.as_str(), "--log-line"]
+            .into_iter()
+            .map(|s| s.to_string());
+
+        let args = Args::parse(&mut it);
+        fs::remove_file(&path).unwrap();
+        let args = args.unwrap();
+
+        assert_eq!(args.port, 9000);
+        assert!(args.strict);
+        assert!(args.log_line);
+    }
+
+    #[test]
+    fn it_errors_on_a_missing_response_file() {
+        let mut it = ["rox", "@/does/not/exist.txt"]
+            .into_iter()
+            .map(|s| s.to_string());
+
+        let err = Args::parse(&mut it).unwrap_err();
+
+        assert!(err.contains("/does/not/exist.txt"));
+    }
+
+    #[test]
+    fn it_can_parse_a_config_file() {
+        let path = std::env::temp_dir().join(format!("rox-config-{}.toml", std::process::id()));
+        fs::write(
+            &path,
+            "port = 9000\nstrict = true\ndrop_header = [\"Cookie\"]\n",
+        )
+        .unwrap();
+
+        let path = path.display().to_string();
+        let mut it = ["rox", "--config", path.as_str(), "--port", "7000"]
+            .into_iter()
+            .map(|s| s.to_string());
+
+        let args = Args::parse(&mut it);
+        fs::remove_file(&path).unwrap();
+        let args = args.unwrap();
+
+        assert_eq!(args.port, 7000);
+        assert!(args.strict);
+        assert_eq!(args.drop_headers, vec!["Cookie"]);
+    }
+
+    #[test]
+    fn it_can_parse_config_with_equals() {
+        let path = std::env::temp_dir().join(format!("rox-config-eq-{}.toml", std::process::id()));
+        fs::write(&path, "port = 9000\n").unwrap();
+
+        let flag = format!("--config={}", path.display());
+        let mut it = ["rox", flag.as_str()].into_iter().map(|s| s.to_string());
+
+        let args = Args::parse(&mut it);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(args.unwrap().port, 9000);
+
+        let mut it = ["rox", "--config=/does/not/exist.toml"]
+            .into_iter()
+            .map(|s| s.to_string());
+        assert!(Args::parse(&mut it).is_err());
+    }
+
+    #[test]
+    fn it_rejects_unknown_config_keys() {
+        let entries = config::parse("colour = \"blue\"").unwrap();
+
+        let err = config_args(entries).unwrap_err();
+
+        assert!(err.contains("colour"));
+    }
+
+    #[test]
+    fn it_can_print_the_effective_config() {
+        let mut it = ["rox", "--port", "9000", "--user", "matthew:morrison"]
+            .into_iter()
+            .map(|s| s.to_string());
+
+        let config = Args::parse(&mut it).unwrap().to_string();
+
+        assert!(config.contains("port = 9000\n"));
+        assert!(config.contains("user = \"matthew:***\"\n"));
+        assert!(!config.contains("morrison"));
+        assert!(config::parse(&config).is_ok());
+    }
+
+    #[test]
+    fn it_can_parse_block_statuses() {
+        let mut it = ["rox", "--block-status", "500", "--block-status-as", "503"]
+            .into_iter()
+            .map(|s| s.to_string());
+
+        let args = Args::parse(&mut it).unwrap();
+
+        assert_eq!(args.block_statuses, vec![500]);
+        assert_eq!(args.block_status_as, StatusCode::ServiceUnavailable);
+    }
+
+    #[test]
+    fn it_rejects_unknown_block_statuses() {
+        let mut it = ["rox", "--block-status", "999"]
+            .into_iter()
+            .map(|s| s.to_string());
+
+        assert!(Args::parse(&mut it).is_err());
+    }
+
+    #[test]
+    fn it_can_parse_map_statuses() {
+        let mut it = ["rox", "--map-status", "418=200", "--map-status", "404=410"]
+            .into_iter()
+            .map(|s| s.to_string());
+
+        let args = Args::parse(&mut it).unwrap();
+
+        assert_eq!(
+            args.map_statuses,
+            vec![(418, StatusCode::OK), (404, StatusCode::Gone)]
+        );
+    }
+
+    #[test]
+    fn it_rejects_malformed_status_mappings() {
+        for mapping in ["404", "404=", "404=999", "abc=200"] {
+            let mut it = ["rox", "--map-status", mapping]
+                .into_iter()
+                .map(|s| s.to_string());
+
+            assert!(Args::parse(&mut it).is_err());
+        }
+    }
+
+    #[test]
+    fn it_can_parse_coalesce() {
+        let mut it = ["rox", "--coalesce"].into_iter().map(|s| s.to_string());
+
+        let args = Args::parse(&mut it).unwrap();
+
+        assert!(args.coalesce);
+    }
+
+    #[test]
+    fn it_can_parse_forwarded_header() {
+        let mut it = ["rox", "--forwarded-header", "RFC7239"]
+            .into_iter()
+            .map(|s| s.to_string());
+
+        let args = Args::parse(&mut it).unwrap();
+
+        assert_eq!(args.forwarded_header, Some(ForwardedHeader::Rfc7239));
+    }
+
+    #[test]
+    fn it_can_parse_follow_redirects() {
+        let mut it = ["rox", "--follow-redirects", "5"]
+            .into_iter()
+            .map(|s| s.to_string());
+
+        let args = Args::parse(&mut it).unwrap();
+
+        assert_eq!(args.follow_redirects, 5);
+    }
+
+    #[test]
+    fn it_can_parse_max_response_bytes() {
+        let mut it = ["rox", "--max-response-bytes", "1048576"]
+            .into_iter()
+            .map(|s| s.to_string());
+
+        let args = Args::parse(&mut it).unwrap();
+
+        assert_eq!(args.max_response_bytes, Some(1048576));
+    }
+
+    #[test]
+    fn it_can_parse_tcp_keepalive() {
+        let mut it = ["rox", "--tcp-keepalive", "60"]
+            .into_iter()
+            .map(|s| s.to_string());
+
+        let args = Args::parse(&mut it).unwrap();
+
+        assert_eq!(args.tcp_keepalive, Some(Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn it_can_parse_drain_signal() {
+        let mut it = ["rox", "--drain-signal", "SIGUSR2"]
+            .into_iter()
+            .map(|s| s.to_string());
+
+        let args = Args::parse(&mut it).unwrap();
+
+        assert_eq!(args.drain_signal, DrainSignal::Usr2);
+
+        let mut it = ["rox", "--drain-signal", "TERM"]
+            .into_iter()
+            .map(|s| s.to_string());
+
+        assert!(Args::parse(&mut it).is_err());
+    }
+
+    #[test]
+    fn it_can_parse_request_id_header() {
+        let mut it = ["rox", "--request-id-header", "X-Trace-Id"]
+            .into_iter()
+            .map(|s| s.to_string());
+
+        let args = Args::parse(&mut it).unwrap();
+
+        assert_eq!(args.request_id_header, "X-Trace-Id");
+    }
+
+    #[test]
+    fn it_can_parse_whoami() {
+        let mut it = ["rox", "--whoami"].into_iter().map(|s| s.to_string());
+
+        let args = Args::parse(&mut it).unwrap();
+
+        assert!(args.whoami);
+    }
+
+    #[test]
+    fn it_can_parse_add_date() {
+        let mut it = ["rox", "--add-date"].into_iter().map(|s| s.to_string());
+
+        let args = Args::parse(&mut it).unwrap();
+
+        assert!(args.add_date);
+    }
+
+    #[test]
+    fn it_can_parse_listen_fd() {
+        let mut it = ["rox", "--listen-fd", "3"]
+            .into_iter()
+            .map(|s| s.to_string());
+
+        let args = Args::parse(&mut it).unwrap();
+
+        assert_eq!(args.listen_fd, Some(3));
+
+        let mut it = ["rox", "--listen-fd", "-1"]
+            .into_iter()
+            .map(|s| s.to_string());
+
+        assert_eq!(
+            Args::parse(&mut it).unwrap_err(),
+            "🚨 Error: listen fd must not be negative 🚨"
+        );
+    }
+
+    #[test]
+    fn it_can_parse_log_sample() {
+        let mut it = ["rox", "--log-sample", "0.1"]
+            .into_iter()
+            .map(|s| s.to_string());
+
+        let args = Args::parse(&mut it).unwrap();
+
+        assert_eq!(args.log_sample, 0.1);
+
+        let mut it = ["rox", "--log-sample", "1.5"]
+            .into_iter()
+            .map(|s| s.to_string());
+
+        assert!(Args::parse(&mut it).is_err());
+    }
+
+    #[test]
+    fn it_can_parse_canonical_headers() {
+        let mut it = ["rox", "--canonical-headers"]
+            .into_iter()
+            .map(|s| s.to_string());
+
+        let args = Args::parse(&mut it).unwrap();
+
+        assert!(args.canonical_headers);
+    }
+
+    #[test]
+    fn it_can_parse_cors() {
+        let mut it = ["rox", "--cors"].into_iter().map(|s| s.to_string());
+
+        let args = Args::parse(&mut it).unwrap();
+
+        assert!(args.cors);
+    }
+
+    #[test]
+    fn it_can_parse_allow_clients() {
+        let mut it = [
+            "rox",
+            "--allow-client",
+            "10.0.0.0/8",
+            "--allow-client",
+            "::1",
+        ]
+        .into_iter()
+        .map(|s| s.to_string());
+
+        let args = Args::parse(&mut it).unwrap();
+
+        assert_eq!(
+            args.allow_clients,
+            vec!["10.0.0.0/8".parse().unwrap(), "::1/128".parse().unwrap()]
+        );
+    }
+
+    #[test]
+    fn it_can_parse_trace_raw() {
+        let mut it = ["rox", "--trace-raw"].into_iter().map(|s| s.to_string());
+
+        let args = Args::parse(&mut it).unwrap();
+
+        assert!(args.trace_raw);
+    }
+
+    #[test]
+    fn it_can_parse_slow_connect_threshold() {
+        let mut it = ["rox", "--slow-connect-threshold", "250"]
+            .into_iter()
+            .map(|s| s.to_string());
+
+        let args = Args::parse(&mut it).unwrap();
+
+        assert_eq!(
+            args.slow_connect_threshold,
+            Some(Duration::from_millis(250))
+        );
+    }
+
+    #[test]
+    fn it_can_parse_user_switch() {
+        let mut it = ["rox", "--user-switch", "65534:65534"]
+            .into_iter()
+            .map(|s| s.to_string());
+
+        let args = Args::parse(&mut it).unwrap();
+
+        assert_eq!(args.user_switch, Some((65534, 65534)));
+
+        let mut it = ["rox", "--user-switch", "nobody"]
+            .into_iter()
+            .map(|s| s.to_string());
+
+        assert!(Args::parse(&mut it).is_err());
+    }
+
+    #[test]
+    fn it_can_parse_echo_upstream_errors() {
+        let mut it = ["rox", "--echo-upstream-errors"]
+            .into_iter()
+            .map(|s| s.to_string());
+
+        let args = Args::parse(&mut it).unwrap();
+
+        assert!(args.echo_upstream_errors);
+    }
+
+    #[test]
+    fn it_can_parse_json_logs() {
+        let mut it = ["rox", "--json-logs"].into_iter().map(|s| s.to_string());
+
+        let args = Args::parse(&mut it).unwrap();
+
+        assert!(args.json_logs);
+    }
+
+    #[test]
+    fn it_can_parse_a_credentials_file() {
+        let path = std::env::temp_dir().join(format!("rox-credentials-{}", std::process::id()));
+        let path = path.to_str().unwrap().to_string();
+        fs::write(
+            &path,
+            "# name:sha256(password)\nmatthew:2bb80d537b1da3e38bd30361aa855686bde0eacd7162fef6a25fe97bf527a25b\n",
+        )
+        .unwrap();
+
+        let mut it = [
+            "rox",
+            "--credentials-file",
+            path.as_str(),
+            "--hash-scheme",
+            "sha256",
+        ]
+        .into_iter()
+        .map(|s| s.to_string());
+
+        let args = Args::parse(&mut it);
+        fs::remove_file(&path).unwrap();
+        let args = args.unwrap();
+
+        let (name, stored) = &args.credentials[0];
+        assert_eq!(name, "matthew");
+        assert!(args.hash_scheme.verify("secret", stored));
+        assert!(!args.hash_scheme.verify("wrong", stored));
+    }
+
+    #[test]
+    fn it_verifies_salted_hashes() {
+        let stored = "sha256$NaCl$8c85a9f29a3a621a098369ea058ccc9133886a9b63b074f1a671309d4dee28bb";
+
+        assert!(HashScheme::Sha256.verify("secret", stored));
+        assert!(!HashScheme::Sha256.verify("wrong", stored));
+        // The digest only matches with the salt it was made with
+        assert!(!HashScheme::Sha256.verify("secret", &stored.replace("NaCl", "pepper")));
+        assert!(!HashScheme::Sha256.verify("secret", "sha256$NaCl"));
+        assert!(!HashScheme::Plain.verify("secret", stored));
+    }
+
+    #[test]
+    fn it_can_parse_max_forward_headers() {
+        let mut it = ["rox", "--max-forward-headers", "50"]
+            .into_iter()
+            .map(|s| s.to_string());
+
+        let args = Args::parse(&mut it).unwrap();
+
+        assert_eq!(args.max_forward_headers, Some(50));
+    }
+
+    #[test]
+    fn it_can_parse_fallback_upstream() {
+        let mut it = ["rox", "--fallback-upstream", "backup.mattymo.dev:3128"]
+            .into_iter()
+            .map(|s| s.to_string());
+
+        let args = Args::parse(&mut it).unwrap();
+
+        assert_eq!(
+            args.fallback_upstream.as_deref(),
+            Some("backup.mattymo.dev:3128")
+        );
+    }
+
+    #[test]
+    fn it_can_parse_upstream_retries() {
+        let mut it = ["rox", "--upstream-retries", "3"]
+            .into_iter()
+            .map(|s| s.to_string());
+
+        let args = Args::parse(&mut it).unwrap();
+
+        assert_eq!(args.upstream_retries, 3);
+    }
+
+    #[test]
+    fn it_can_parse_landing() {
+        let mut it = ["rox", "--landing"].into_iter().map(|s| s.to_string());
+
+        let args = Args::parse(&mut it).unwrap();
+
+        assert!(args.landing);
+    }
+
+    #[test]
+    fn it_can_parse_deny_user_agents() {
+        let mut it = [
+            "rox",
+            "--deny-user-agent",
+            "curl",
+            "--deny-user-agent",
+            "sqlmap",
+        ]
+        .into_iter()
+        .map(|s| s.to_string());
+
+        let args = Args::parse(&mut it).unwrap();
+
+        assert_eq!(args.deny_user_agents, vec!["curl", "sqlmap"]);
+    }
+
+    #[test]
+    fn it_can_parse_log_sni() {
+        let mut it = ["rox", "--log-sni"].into_iter().map(|s| s.to_string());
+
+        let args = Args::parse(&mut it).unwrap();
+
+        assert!(args.log_sni);
+    }
+
+    #[test]
+    fn it_can_parse_max_request_body_time() {
+        let mut it = ["rox", "--max-request-body-time", "10"]
+            .into_iter()
+            .map(|s| s.to_string());
+
+        let args = Args::parse(&mut it).unwrap();
+
+        assert_eq!(args.max_request_body_time, Some(Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn it_can_parse_max_request_bytes() {
+        let mut it = ["rox", "--max-request-bytes", "65536"]
+            .into_iter()
+            .map(|s| s.to_string());
+
+        let args = Args::parse(&mut it).unwrap();
+
+        assert_eq!(args.max_request_bytes, Some(65536));
+    }
+
+    #[test]
+    fn it_can_parse_breaker_options() {
+        let mut it = [
+            "rox",
+            "--breaker-threshold",
+            "5",
+            "--breaker-window",
+            "20",
+            "--breaker-cooldown",
+            "60",
+        ]
+        .into_iter()
+        .map(|s| s.to_string());
+
+        let args = Args::parse(&mut it).unwrap();
+
+        assert_eq!(args.breaker_threshold, Some(5));
+        assert_eq!(args.breaker_window, Duration::from_secs(20));
+        assert_eq!(args.breaker_cooldown, Duration::from_secs(60));
+    }
+
+    #[test]
+    fn it_can_parse_strict() {
+        let mut it = ["rox", "--strict"].into_iter().map(|s| s.to_string());
+
+        let args = Args::parse(&mut it).unwrap();
+
+        assert!(args.strict);
+    }
+
+    #[test]
+    fn it_can_parse_warmup_time() {
+        let mut it = ["rox", "--warmup-time", "5"]
+            .into_iter()
+            .map(|s| s.to_string());
+
+        let args = Args::parse(&mut it).unwrap();
+
+        assert_eq!(args.warmup_time, Some(Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn it_can_parse_max_lifetime() {
+        let mut it = ["rox", "--max-lifetime", "30"]
+            .into_iter()
+            .map(|s| s.to_string());
+
+        let args = Args::parse(&mut it).unwrap();
+
+        assert_eq!(args.max_lifetime, Some(Duration::from_secs(30)));
+    }
 }
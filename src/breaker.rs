@@ -0,0 +1,257 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::log::Tag;
+
+/// How many hosts a breaker tracks before it prunes stale entries and stops
+/// counting new failures, so clients dialing endless made-up names can't
+/// grow it without bound.
+const MAX_HOSTS: usize = 10_000;
+
+/// Stops trying upstreams that keep failing to connect. After `threshold`
+/// consecutive failures within `window` a host's circuit opens and requests
+/// for it are refused for `cooldown`; after that one probe is let through,
+/// and its result decides whether the circuit closes or opens again.
+pub struct Breaker {
+    threshold: Option<usize>,
+    window: Duration,
+    cooldown: Duration,
+    hosts: Mutex<HashMap<String, Circuit>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Circuit {
+    /// Failing, but not yet enough to open
+    Closed { failures: usize, since: Instant },
+    /// Refusing requests until the cooldown is over
+    Open { until: Instant },
+    /// A probe is out; anyone else waits for it, or for another cooldown
+    /// if it never reports back
+    HalfOpen { until: Instant },
+}
+
+impl Breaker {
+    /// A breaker that opens after `threshold` failures, or never if `None`.
+    pub fn new(threshold: Option<usize>, window: Duration, cooldown: Duration) -> Self {
+        Self {
+            threshold,
+            window,
+            cooldown,
+            hosts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Whether to try connecting to `host` at all, logging any change to its
+    /// circuit under `tag`.
+    pub fn allow(&self, host: &str, tag: &Tag) -> bool {
+        self.allow_at(host, tag, Instant::now())
+    }
+
+    /// Records how connecting to `host` went.
+    pub fn record(&self, host: &str, ok: bool, tag: &Tag) {
+        self.record_at(host, ok, tag, Instant::now())
+    }
+
+    fn allow_at(&self, host: &str, tag: &Tag, now: Instant) -> bool {
+        if self.threshold.is_none() {
+            return true;
+        }
+
+        let mut hosts = self.hosts.lock().unwrap();
+
+        match hosts.get(host) {
+            Some(Circuit::Open { until } | Circuit::HalfOpen { until }) if now < *until => false,
+            Some(Circuit::Open { .. } | Circuit::HalfOpen { .. }) => {
+                log!(tag => "Circuit for {} half-open, probing", host);
+                hosts.insert(
+                    host.to_string(),
+                    Circuit::HalfOpen {
+                        until: now + self.cooldown,
+                    },
+                );
+                true
+            }
+            _ => true,
+        }
+    }
+
+    fn record_at(&self, host: &str, ok: bool, tag: &Tag, now: Instant) {
+        let threshold = match self.threshold {
+            Some(threshold) => threshold,
+            None => return,
+        };
+
+        let mut hosts = self.hosts.lock().unwrap();
+
+        if ok {
+            if let Some(Circuit::HalfOpen { .. }) = hosts.remove(host) {
+                log!(tag => "Circuit for {} closed", host);
+            }
+            return;
+        }
+
+        let (failures, since) = match hosts.get(host) {
+            Some(Circuit::Closed { failures, since }) if now - *since <= self.window => {
+                (failures + 1, *since)
+            }
+            // Requests that got in before it opened can still fail
+            Some(Circuit::Open { .. }) => return,
+            // The probe failed, so open again straight away
+            Some(Circuit::HalfOpen { .. }) => (threshold, now),
+            _ => (1, now),
+        };
+
+        let circuit = if failures >= threshold {
+            log!(
+                tag => "Circuit for {} open after {} failure(s), refusing for {}s",
+                host,
+                failures,
+                self.cooldown.as_secs()
+            );
+            Circuit::Open {
+                until: now + self.cooldown,
+            }
+        } else {
+            Circuit::Closed { failures, since }
+        };
+
+        if hosts.len() >= MAX_HOSTS && !hosts.contains_key(host) {
+            // Failures outside their window and elapsed cooldowns decide
+            // nothing any more
+            hosts.retain(|_, circuit| match circuit {
+                Circuit::Closed { since, .. } => now - *since <= self.window,
+                Circuit::Open { until } | Circuit::HalfOpen { until } => now < *until,
+            });
+
+            // Forgetting a failure costs less than forgetting an open circuit
+            if hosts.len() >= MAX_HOSTS && matches!(circuit, Circuit::Closed { .. }) {
+                return;
+            }
+        }
+
+        hosts.insert(host.to_string(), circuit);
+    }
+
+    #[cfg(test)]
+    fn circuit(&self, host: &str) -> Option<Circuit> {
+        self.hosts.lock().unwrap().get(host).copied()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const WINDOW: Duration = Duration::from_secs(10);
+    const COOLDOWN: Duration = Duration::from_secs(30);
+    const TAG: &Tag = &Tag {
+        id: String::new(),
+        json: false,
+    };
+
+    fn breaker(threshold: usize) -> Breaker {
+        Breaker::new(Some(threshold), WINDOW, COOLDOWN)
+    }
+
+    #[test]
+    fn it_opens_after_consecutive_failures() {
+        let breaker = breaker(3);
+        let now = Instant::now();
+
+        for _ in 0..2 {
+            assert!(breaker.allow_at("a:80", TAG, now));
+            breaker.record_at("a:80", false, TAG, now);
+        }
+        assert!(breaker.allow_at("a:80", TAG, now));
+        breaker.record_at("a:80", false, TAG, now);
+
+        assert!(!breaker.allow_at("a:80", TAG, now + Duration::from_secs(1)));
+        assert!(breaker.allow_at("b:80", TAG, now + Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn it_forgets_failures_outside_the_window() {
+        let breaker = breaker(2);
+        let now = Instant::now();
+
+        breaker.record_at("a:80", false, TAG, now);
+        breaker.record_at("a:80", false, TAG, now + WINDOW + Duration::from_secs(1));
+
+        assert!(breaker.allow_at("a:80", TAG, now + WINDOW + Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn it_resets_on_success() {
+        let breaker = breaker(2);
+        let now = Instant::now();
+
+        breaker.record_at("a:80", false, TAG, now);
+        breaker.record_at("a:80", true, TAG, now);
+        breaker.record_at("a:80", false, TAG, now);
+
+        assert!(breaker.allow_at("a:80", TAG, now));
+    }
+
+    #[test]
+    fn it_half_opens_after_the_cooldown() {
+        let breaker = breaker(1);
+        let now = Instant::now();
+
+        breaker.record_at("a:80", false, TAG, now);
+        assert!(!breaker.allow_at("a:80", TAG, now + COOLDOWN - Duration::from_secs(1)));
+
+        // One probe goes through, and no one else until it reports back
+        let later = now + COOLDOWN;
+        assert!(breaker.allow_at("a:80", TAG, later));
+        assert!(matches!(
+            breaker.circuit("a:80"),
+            Some(Circuit::HalfOpen { .. })
+        ));
+        assert!(!breaker.allow_at("a:80", TAG, later));
+
+        breaker.record_at("a:80", false, TAG, later);
+        assert!(matches!(
+            breaker.circuit("a:80"),
+            Some(Circuit::Open { .. })
+        ));
+        assert!(!breaker.allow_at("a:80", TAG, later + Duration::from_secs(1)));
+
+        let later = later + COOLDOWN;
+        assert!(breaker.allow_at("a:80", TAG, later));
+        breaker.record_at("a:80", true, TAG, later);
+        assert_eq!(breaker.circuit("a:80"), None);
+        assert!(breaker.allow_at("a:80", TAG, later));
+    }
+
+    #[test]
+    fn it_stops_growing_past_the_host_limit() {
+        let breaker = breaker(2);
+        let now = Instant::now();
+
+        for i in 0..MAX_HOSTS + 10 {
+            breaker.record_at(&format!("{}.invalid:443", i), false, TAG, now);
+        }
+        assert_eq!(breaker.hosts.lock().unwrap().len(), MAX_HOSTS);
+
+        // Once their window has passed, the old failures make way
+        let later = now + WINDOW + Duration::from_secs(1);
+        breaker.record_at("new.invalid:443", false, TAG, later);
+        assert_eq!(breaker.hosts.lock().unwrap().len(), 1);
+        assert!(breaker.circuit("new.invalid:443").is_some());
+    }
+
+    #[test]
+    fn it_never_opens_without_a_threshold() {
+        let breaker = Breaker::new(None, WINDOW, COOLDOWN);
+        let now = Instant::now();
+
+        for _ in 0..10 {
+            breaker.record_at("a:80", false, TAG, now);
+        }
+
+        assert!(breaker.allow_at("a:80", TAG, now));
+    }
+}
@@ -0,0 +1,111 @@
+use std::{fmt::Display, net::IpAddr, str::FromStr};
+
+/// An IPv4 or IPv6 address range such as `10.0.0.0/8` or `fd00::/8`. A bare
+/// address is a range of one.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Cidr {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    pub fn contains(&self, addr: IpAddr) -> bool {
+        // Clients on a dual-stack socket show up as ::ffff:a.b.c.d
+        match (self.addr, addr.to_canonical()) {
+            (IpAddr::V4(net), IpAddr::V4(addr)) => {
+                masked(u32::from(net).into(), 32, self.prefix)
+                    == masked(u32::from(addr).into(), 32, self.prefix)
+            }
+            (IpAddr::V6(net), IpAddr::V6(addr)) => {
+                masked(net.into(), 128, self.prefix) == masked(addr.into(), 128, self.prefix)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Keeps the top `prefix` bits of a `bits` wide address.
+fn masked(addr: u128, bits: u8, prefix: u8) -> u128 {
+    match bits - prefix {
+        0 => addr,
+        n if n >= 128 => 0,
+        n => addr >> n << n,
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (addr, prefix) = match s.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (s, None),
+        };
+
+        let addr: IpAddr = addr
+            .parse()
+            .map_err(|_| format!("🚨 Invalid address in {} 🚨", s))?;
+        let bits = if addr.is_ipv4() { 32 } else { 128 };
+
+        let prefix = match prefix {
+            Some(prefix) => prefix
+                .parse()
+                .ok()
+                .filter(|prefix| *prefix <= bits)
+                .ok_or_else(|| format!("🚨 Invalid prefix length in {} 🚨", s))?,
+            None => bits,
+        };
+
+        Ok(Cidr { addr, prefix })
+    }
+}
+
+impl Display for Cidr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn it_can_match_ipv4_ranges() {
+        let cidr: Cidr = "10.1.0.0/16".parse().unwrap();
+
+        assert!(cidr.contains(ip("10.1.255.3")));
+        assert!(cidr.contains(ip("::ffff:10.1.0.1")));
+        assert!(!cidr.contains(ip("10.2.0.1")));
+        assert!(!cidr.contains(ip("::1")));
+        assert!("0.0.0.0/0".parse::<Cidr>().unwrap().contains(ip("8.8.8.8")));
+        assert!(
+            "127.0.0.1"
+                .parse::<Cidr>()
+                .unwrap()
+                .contains(ip("127.0.0.1"))
+        );
+    }
+
+    #[test]
+    fn it_can_match_ipv6_ranges() {
+        let cidr: Cidr = "fd00::/8".parse().unwrap();
+
+        assert!(cidr.contains(ip("fd12:3456::1")));
+        assert!(!cidr.contains(ip("fe80::1")));
+        assert!(!cidr.contains(ip("10.0.0.1")));
+        assert!("::/0".parse::<Cidr>().unwrap().contains(ip("2001:db8::1")));
+    }
+
+    #[test]
+    fn it_rejects_invalid_ranges() {
+        assert!("10.0.0.0/33".parse::<Cidr>().is_err());
+        assert!("10.0.0/8".parse::<Cidr>().is_err());
+        assert!("fd00::/129".parse::<Cidr>().is_err());
+        assert_eq!("fd00::/8".parse::<Cidr>().unwrap().to_string(), "fd00::/8");
+    }
+}
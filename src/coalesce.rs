@@ -0,0 +1,135 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{Arc, Mutex},
+};
+use tokio::sync::watch;
+
+use crate::http::Response;
+
+type Receiver = watch::Receiver<Option<Arc<Response>>>;
+
+/// Shares one upstream fetch between concurrent requests for the same key.
+/// The first caller fetches; everyone who arrives while it's in flight
+/// waits for and receives the same response, unless `shareable` says it's
+/// only fit for the first caller, in which case they each fetch their own.
+#[derive(Default)]
+pub struct Inflight {
+    map: Mutex<HashMap<String, Receiver>>,
+}
+
+impl Inflight {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub async fn get_or_fetch<F, Fut>(
+        &self,
+        key: String,
+        shareable: fn(&Response) -> bool,
+        fetch: F,
+    ) -> Arc<Response>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Response>,
+    {
+        let (tx, mut rx) = {
+            let mut map = self.map.lock().unwrap();
+
+            match map.get(&key) {
+                Some(rx) => (None, rx.clone()),
+                None => {
+                    let (tx, rx) = watch::channel(None);
+                    map.insert(key.clone(), rx.clone());
+                    (Some(tx), rx)
+                }
+            }
+        };
+
+        let tx = match tx {
+            Some(tx) => tx,
+            None => {
+                if let Ok(response) = rx.wait_for(Option::is_some).await {
+                    return response.clone().unwrap();
+                }
+
+                // The fetching request went away before finishing, or
+                // got a response it can't share
+                return Arc::new(fetch().await);
+            }
+        };
+
+        let _remove = Remove {
+            inflight: self,
+            key,
+        };
+        let response = Arc::new(fetch().await);
+        if shareable(&response) {
+            let _ = tx.send(Some(response.clone()));
+        }
+
+        response
+    }
+}
+
+struct Remove<'a> {
+    inflight: &'a Inflight,
+    key: String,
+}
+
+impl Drop for Remove<'_> {
+    fn drop(&mut self) {
+        self.inflight.map.lock().unwrap().remove(&self.key);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Duration,
+    };
+
+    use crate::http::{ResponseBuilder, StatusCode};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn it_shares_a_single_fetch() {
+        let inflight = Arc::new(Inflight::new());
+        let fetches = Arc::new(AtomicUsize::new(0));
+
+        let tasks: Vec<_> = (0..5)
+            .map(|_| {
+                let inflight = inflight.clone();
+                let fetches = fetches.clone();
+
+                tokio::spawn(async move {
+                    let key = String::from("GET http://mattymo.dev/");
+                    inflight
+                        .get_or_fetch(
+                            key,
+                            |_| true,
+                            || async {
+                                fetches.fetch_add(1, Ordering::SeqCst);
+                                tokio::time::sleep(Duration::from_millis(100)).await;
+                                ResponseBuilder::new()
+                                    .add_status_code(StatusCode::OK)
+                                    .add_body("shared")
+                                    .build()
+                                    .unwrap()
+                            },
+                        )
+                        .await
+                })
+            })
+            .collect();
+
+        for task in tasks {
+            assert_eq!(task.await.unwrap().body, b"shared");
+        }
+
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+        assert!(inflight.map.lock().unwrap().is_empty());
+    }
+}
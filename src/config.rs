@@ -0,0 +1,245 @@
+use std::{env, fs};
+
+/// Finds the value of a `${VAR}`.
+type Lookup<'a> = dyn Fn(&str) -> Option<String> + 'a;
+
+#[derive(Debug, PartialEq)]
+pub enum Value {
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Bool(bool),
+    Array(Vec<Value>),
+}
+
+/// Loads a config file written in a small subset of TOML: top-level
+/// `key = value` pairs where a value is a string, number, boolean, or a
+/// single-line array of those. `${VAR}` in a basic string is replaced with
+/// the environment variable `VAR`, so secrets can stay out of the file.
+pub fn load(path: &str) -> Result<Vec<(String, Value)>, String> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| format!("🚨 Error reading config {}: {} 🚨", path, e))?;
+
+    parse(&contents).map_err(|e| format!("🚨 Error parsing config {}: {} 🚨", path, e))
+}
+
+pub fn parse(contents: &str) -> Result<Vec<(String, Value)>, String> {
+    parse_with(contents, &|name| env::var(name).ok())
+}
+
+/// Like `parse`, but looks `${VAR}`s up with `lookup` instead of in the
+/// environment.
+fn parse_with(contents: &str, lookup: &Lookup<'_>) -> Result<Vec<(String, Value)>, String> {
+    let mut entries = Vec::new();
+
+    for (i, line) in contents.lines().enumerate() {
+        let line = strip_comment(line).trim();
+
+        if line.is_empty() {
+            continue;
+        }
+
+        if line.starts_with('[') {
+            return Err(format!("line {}: tables are not supported", i + 1));
+        }
+
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| format!("line {}: expected key = value", i + 1))?;
+
+        let key = key.trim();
+        if key.is_empty() {
+            return Err(format!("line {}: missing key", i + 1));
+        }
+
+        let (value, rest) =
+            parse_value(value.trim(), lookup).map_err(|e| format!("line {}: {}", i + 1, e))?;
+        if !rest.trim().is_empty() {
+            return Err(format!(
+                "line {}: unexpected trailing {}",
+                i + 1,
+                rest.trim()
+            ));
+        }
+
+        entries.push((key.to_string(), value));
+    }
+
+    Ok(entries)
+}
+
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+
+    for (i, c) in line.char_indices() {
+        match (quote, c) {
+            (None, '#') => return &line[..i],
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            _ => {}
+        }
+    }
+
+    line
+}
+
+fn parse_value<'a>(s: &'a str, lookup: &Lookup<'_>) -> Result<(Value, &'a str), String> {
+    if let Some(rest) = s.strip_prefix('"') {
+        return parse_basic_string(rest, lookup);
+    }
+
+    if let Some(rest) = s.strip_prefix('\'') {
+        let end = rest.find('\'').ok_or("unterminated string")?;
+        return Ok((Value::String(rest[..end].to_string()), &rest[end + 1..]));
+    }
+
+    if let Some(mut rest) = s.strip_prefix('[') {
+        let mut values = Vec::new();
+
+        loop {
+            rest = rest.trim_start();
+
+            if let Some(r) = rest.strip_prefix(']') {
+                return Ok((Value::Array(values), r));
+            }
+
+            let (value, r) = parse_value(rest, lookup)?;
+            values.push(value);
+            rest = r.trim_start();
+
+            match rest.strip_prefix(',') {
+                Some(r) => rest = r,
+                None if rest.starts_with(']') => {}
+                None => return Err(String::from("expected , or ] in array")),
+            }
+        }
+    }
+
+    let end = s.find([',', ']']).unwrap_or(s.len());
+    let (token, rest) = (s[..end].trim(), &s[end..]);
+
+    match token {
+        "true" => Ok((Value::Bool(true), rest)),
+        "false" => Ok((Value::Bool(false), rest)),
+        _ => {
+            let number = token.replace('_', "");
+
+            match number.parse() {
+                Ok(n) => Ok((Value::Integer(n), rest)),
+                Err(_) if number.contains(['.', 'e', 'E']) => number
+                    .parse()
+                    .map(|n| (Value::Float(n), rest))
+                    .map_err(|_| format!("invalid value {}", token)),
+                Err(_) => Err(format!("invalid value {}", token)),
+            }
+        }
+    }
+}
+
+fn parse_basic_string<'a>(s: &'a str, lookup: &Lookup<'_>) -> Result<(Value, &'a str), String> {
+    let mut out = String::new();
+    let mut chars = s.char_indices();
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Ok((Value::String(out), &s[i + 1..])),
+            '\\' => match chars.next() {
+                Some((_, '"')) => out.push('"'),
+                Some((_, '\\')) => out.push('\\'),
+                Some((_, 'n')) => out.push('\n'),
+                Some((_, 't')) => out.push('\t'),
+                Some((_, c)) => return Err(format!("invalid escape \\{}", c)),
+                None => break,
+            },
+            '$' if s[i + 1..].starts_with('{') => {
+                let name = match s[i + 2..].find('}') {
+                    Some(end) => &s[i + 2..i + 2 + end],
+                    None => return Err(String::from("unterminated ${")),
+                };
+
+                let value = lookup(name).ok_or_else(|| format!("undefined variable {}", name))?;
+                out.push_str(&value);
+
+                // Skip past the closing brace
+                chars.nth(name.chars().count() + 1);
+            }
+            c => out.push(c),
+        }
+    }
+
+    Err(String::from("unterminated string"))
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn it_can_parse_a_config() {
+        let raw = concat!(
+            "# rox config\n",
+            "port = 9000\n",
+            "user = \"matthew:p#ss\" # trailing comment\n",
+            "strict = true\n",
+            "log_sample = 0.25\n",
+            "drop_header = [\"Cookie\", 'Referer']\n",
+        );
+
+        let entries = parse(raw).unwrap();
+
+        assert_eq!(
+            entries,
+            vec![
+                (String::from("port"), Value::Integer(9000)),
+                (String::from("user"), Value::String("matthew:p#ss".into())),
+                (String::from("strict"), Value::Bool(true)),
+                (String::from("log_sample"), Value::Float(0.25)),
+                (
+                    String::from("drop_header"),
+                    Value::Array(vec![
+                        Value::String("Cookie".into()),
+                        Value::String("Referer".into()),
+                    ])
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn it_expands_environment_variables() {
+        let vars = HashMap::from([("ROX_TEST_CONFIG_USER", "matthew:secret")]);
+        let lookup = |name: &str| vars.get(name).map(|value| value.to_string());
+        let parse = |contents| parse_with(contents, &lookup);
+
+        let entries =
+            parse("user = \"${ROX_TEST_CONFIG_USER}\"\nmessage = \"hi ${ROX_TEST_CONFIG_USER}!\"")
+                .unwrap();
+
+        assert_eq!(
+            entries,
+            vec![
+                (String::from("user"), Value::String("matthew:secret".into())),
+                (
+                    String::from("message"),
+                    Value::String("hi matthew:secret!".into())
+                ),
+            ]
+        );
+        assert_eq!(
+            parse("user = '${ROX_TEST_CONFIG_USER}'").unwrap()[0].1,
+            Value::String("${ROX_TEST_CONFIG_USER}".into())
+        );
+        assert!(parse("user = \"${ROX_TEST_CONFIG_UNDEFINED}\"").is_err());
+        assert!(parse("user = \"${ROX_TEST_CONFIG_USER\"").is_err());
+    }
+
+    #[test]
+    fn it_rejects_invalid_lines() {
+        assert!(parse("port 9000").is_err());
+        assert!(parse("[server]").is_err());
+        assert!(parse("user = \"unterminated").is_err());
+        assert!(parse("port = nine").is_err());
+    }
+}
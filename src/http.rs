@@ -1,13 +1,87 @@
+mod cache_control;
+mod chunked;
+mod date;
 mod headers;
 mod request;
 mod response;
 
-use std::fmt::Display;
+use std::{fmt::Display, time::Duration};
 
+use crate::log::Tag;
+
+pub use cache_control::*;
+pub use date::*;
 pub use headers::*;
 pub use request::*;
 pub use response::*;
 
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    pub strict: bool,
+    pub max_body_bytes: Option<usize>,
+    /// Give up on a message whose start line and headers run past this many
+    /// bytes without ending.
+    pub max_head_bytes: Option<usize>,
+    /// Give up on a request whose body takes longer than this to arrive once
+    /// its headers are in.
+    pub max_body_time: Option<Duration>,
+    /// Log the raw head of each message, escaped, before parsing it.
+    pub trace_raw: bool,
+    /// Headers whose values that log masks as `***`.
+    pub redact_headers: Vec<String>,
+    /// What to tag the lines logged while parsing with.
+    pub tag: Tag,
+}
+
+/// Parses a `Content-Length` value, which has to be plain digits (RFC 9110
+/// section 8.6) apart from surrounding whitespace; `+5` and `5x` are not
+/// lengths.
+pub fn parse_content_length(value: &str) -> Option<usize> {
+    let value = value.trim();
+
+    if value.is_empty() || !value.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    value.parse().ok()
+}
+
+/// Escapes `bytes` so CRLFs, stray whitespace and non-ASCII bytes are all
+/// visible in a log line, e.g. `GET / HTTP/1.1\r\n`.
+pub fn escape_raw(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .flat_map(|b| std::ascii::escape_default(*b))
+        .map(char::from)
+        .collect()
+}
+
+/// `head` with the value of every field named in `names` masked as `***`,
+/// leaving everything else, malformed or not, byte for byte.
+pub(crate) fn redact_raw(head: &[u8], names: &[String]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(head.len());
+
+    for line in head.split_inclusive(|b| *b == b'\n') {
+        let name = line.iter().position(|b| *b == b':').map(|colon| {
+            let name = String::from_utf8_lossy(&line[..colon]);
+            (colon, name.trim().to_string())
+        });
+
+        match name {
+            Some((colon, name)) if names.iter().any(|n| n.eq_ignore_ascii_case(&name)) => {
+                // Keep the line ending, whatever it is
+                let ending = &line[line.trim_ascii_end().len()..];
+                out.extend_from_slice(&line[..=colon]);
+                out.extend_from_slice(b" ***");
+                out.extend_from_slice(ending);
+            }
+            _ => out.extend_from_slice(line),
+        }
+    }
+
+    out
+}
+
 #[repr(u16)]
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum StatusCode {
@@ -88,6 +162,10 @@ pub enum StatusCode {
 }
 
 impl StatusCode {
+    pub fn from_u16(code: u16) -> StatusCode {
+        StatusCode::parse(&code.to_string())
+    }
+
     fn parse(code: &str) -> StatusCode {
         match code {
             // Informational
@@ -122,14 +200,16 @@ impl StatusCode {
             // Client Error
             "400" => StatusCode::BadRequest,
             "401" => StatusCode::Unauthorized,
+            "402" => StatusCode::PaymentRequired,
+            "403" => StatusCode::Forbidden,
             "404" => StatusCode::NotFound,
             "405" => StatusCode::MethodNotAllowed,
             "406" => StatusCode::NotAcceptable,
             "407" => StatusCode::ProxyAuthenticationRequired,
-            "410" => StatusCode::Gone,
-            "411" => StatusCode::LengthRequired,
             "408" => StatusCode::RequestTimeout,
             "409" => StatusCode::Conflict,
+            "410" => StatusCode::Gone,
+            "411" => StatusCode::LengthRequired,
             "412" => StatusCode::PreconditionFailed,
             "413" => StatusCode::ContentTooLarge,
             "414" => StatusCode::URLTooLong,
@@ -239,13 +319,12 @@ impl StatusCode {
             StatusCode::NotExtended => "Not Extended",
             StatusCode::NetworkAuthenticationRequired => "Network Authentication Required",
             StatusCode::Unknown => "Unknown",
-            _ => "Unknown",
         }
     }
 }
 
 impl Display for StatusCode {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.clone() as u16)
+        write!(f, "{}", *self as u16)
     }
 }
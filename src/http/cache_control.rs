@@ -0,0 +1,71 @@
+use super::{parse_list, parse_params};
+
+#[derive(Debug, Default, PartialEq)]
+pub struct CacheControl {
+    pub no_store: bool,
+    pub no_cache: bool,
+    pub max_age: Option<u64>,
+    pub public: bool,
+    pub private: bool,
+    pub must_revalidate: bool,
+}
+
+impl CacheControl {
+    /// Parses a `Cache-Control` value, ignoring unknown directives and any
+    /// `max-age` that isn't a number.
+    pub fn parse(value: &str) -> CacheControl {
+        let mut cache_control = CacheControl::default();
+
+        for (directive, arg) in parse_list(value).into_iter().flat_map(parse_params) {
+            match directive.to_lowercase().as_str() {
+                "no-store" => cache_control.no_store = true,
+                "no-cache" => cache_control.no_cache = true,
+                "public" => cache_control.public = true,
+                "private" => cache_control.private = true,
+                "must-revalidate" => cache_control.must_revalidate = true,
+                "max-age" => cache_control.max_age = arg.and_then(|a| a.parse().ok()),
+                _ => {}
+            }
+        }
+
+        cache_control
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_can_parse_cache_control() {
+        let cache_control = CacheControl::parse("public, max-age=0, must-revalidate");
+
+        assert_eq!(
+            cache_control,
+            CacheControl {
+                public: true,
+                max_age: Some(0),
+                must_revalidate: true,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn it_can_parse_no_store() {
+        let cache_control = CacheControl::parse("no-store");
+
+        assert!(cache_control.no_store);
+        assert!(!cache_control.no_cache);
+        assert_eq!(cache_control.max_age, None);
+    }
+
+    #[test]
+    fn it_can_parse_mixed_case_directives() {
+        let cache_control = CacheControl::parse("Private, No-Cache, Max-Age=\"600\"");
+
+        assert!(cache_control.private);
+        assert!(cache_control.no_cache);
+        assert_eq!(cache_control.max_age, Some(600));
+    }
+}
@@ -0,0 +1,201 @@
+use std::io;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt};
+
+use super::{Headers, parse_list};
+use crate::log::Tag;
+
+/// Fields a trailer must not set (RFC 9110 section 6.5.1): framing, routing,
+/// credentials, controls, and what proxies add, since by the time trailers
+/// arrive the head has already been checked and acted on.
+const FORBIDDEN_TRAILERS: [&str; 32] = [
+    "Age",
+    "Authorization",
+    "Cache-Control",
+    "Connection",
+    "Content-Encoding",
+    "Content-Length",
+    "Content-Range",
+    "Content-Type",
+    "Cookie",
+    "Date",
+    "Expect",
+    "Expires",
+    "Forwarded",
+    "Host",
+    "If-Match",
+    "If-Modified-Since",
+    "If-None-Match",
+    "If-Range",
+    "If-Unmodified-Since",
+    "Location",
+    "Max-Forwards",
+    "Pragma",
+    "Proxy-Authenticate",
+    "Proxy-Authorization",
+    "Range",
+    "Retry-After",
+    "Set-Cookie",
+    "TE",
+    "Trailer",
+    "Transfer-Encoding",
+    "Vary",
+    "WWW-Authenticate",
+];
+
+/// How many chunks a body may come in, whatever its limits, since each one
+/// costs a read and a size line no matter how little it carries.
+const MAX_CHUNKS: usize = 4096;
+
+/// How much framing (size lines, extensions and the CRLFs around the data)
+/// a body may carry, whatever its limits.
+const MAX_FRAMING_BYTES: usize = 256 * 1024;
+
+/// Whether a `Transfer-Encoding` value is `chunked` alone, the only coding
+/// rox can undo.
+pub(crate) fn is_chunked(codings: &str) -> bool {
+    matches!(parse_list(codings)[..], [coding] if coding.eq_ignore_ascii_case("chunked"))
+}
+
+/// Decodes a `Transfer-Encoding: chunked` body off `reader`, returning the
+/// data without its framing and adding to `headers` any trailers it announced
+/// in `Trailer` that a trailer may carry; the rest are dropped. Nothing past
+/// the final CRLF is consumed, and dropped trailers are logged under `tag`.
+/// Each size line, and the trailers as a whole, may run to `max_line_bytes`;
+/// the decoded body to `max_body_bytes`, past which the error is
+/// `FileTooLarge`. The framing as a whole may run to whichever of the two is
+/// larger, and never past `MAX_FRAMING_BYTES` or `MAX_CHUNKS` chunks, so
+/// padded, extended or tiny chunks can't make rox read far more than the body
+/// it keeps.
+pub(crate) async fn read_chunked<R>(
+    reader: &mut R,
+    headers: &mut Headers,
+    max_body_bytes: usize,
+    max_line_bytes: usize,
+    tag: &Tag,
+) -> io::Result<Vec<u8>>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let mut body = Vec::new();
+    let max_framing_bytes = max_body_bytes.max(max_line_bytes).min(MAX_FRAMING_BYTES);
+    let mut framing_bytes = 0usize;
+    let mut chunks = 0;
+
+    loop {
+        // The last chunk, of size 0, counts too
+        chunks += 1;
+        if chunks > MAX_CHUNKS {
+            return Err(invalid(format!("More than {} chunks", MAX_CHUNKS)));
+        }
+
+        let line = read_line(reader, max_line_bytes.min(max_framing_bytes)).await?;
+
+        // The size line and the CRLF closing its chunk
+        framing_bytes = framing_bytes.saturating_add(line.len() + 4);
+        if framing_bytes > max_framing_bytes {
+            return Err(invalid("Too much chunk framing".into()));
+        }
+
+        // Extensions like `5;name=value` follow the size and are ignored
+        let size = line.split(';').next().unwrap_or_default().trim();
+        let size = match size {
+            "" => None,
+            size if size.bytes().all(|b| b.is_ascii_hexdigit()) => {
+                usize::from_str_radix(size, 16).ok()
+            }
+            _ => None,
+        }
+        .ok_or_else(|| invalid(format!("Invalid chunk size: {:?}", line)))?;
+
+        if size == 0 {
+            break;
+        }
+
+        if body.len().saturating_add(size) > max_body_bytes {
+            return Err(io::Error::new(
+                io::ErrorKind::FileTooLarge,
+                "Chunked body too large",
+            ));
+        }
+
+        let before = body.len();
+        (&mut *reader)
+            .take(size as u64)
+            .read_to_end(&mut body)
+            .await?;
+
+        if body.len() - before < size {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+
+        if !read_line(reader, 0).await?.is_empty() {
+            return Err(invalid("Chunk longer than its size".into()));
+        }
+    }
+
+    let announced: Vec<String> = headers
+        .get("Trailer")
+        .map(|names| parse_list(names).into_iter().map(String::from).collect())
+        .unwrap_or_default();
+    let mut trailer_bytes = 0;
+
+    loop {
+        let line = read_line(reader, max_line_bytes.saturating_sub(trailer_bytes)).await?;
+
+        if line.is_empty() {
+            break;
+        }
+
+        let (key, value) = line
+            .split_once(':')
+            .ok_or_else(|| invalid(format!("Invalid trailer: {:?}", line)))?;
+
+        let key = key.trim();
+        trailer_bytes += line.len() + 2;
+
+        if allowed_trailer(key, &announced) {
+            headers.append(key, value.trim());
+        } else {
+            log!(tag => "Dropping trailer: {}", key);
+        }
+    }
+
+    Ok(body)
+}
+
+fn allowed_trailer(name: &str, announced: &[String]) -> bool {
+    let forbidden = FORBIDDEN_TRAILERS
+        .iter()
+        .any(|field| field.eq_ignore_ascii_case(name))
+        || name.to_ascii_lowercase().starts_with("x-forwarded-");
+
+    !forbidden
+        && announced
+            .iter()
+            .any(|field| field.eq_ignore_ascii_case(name))
+}
+
+/// One CRLF-terminated line, without the CRLF.
+async fn read_line<R>(reader: &mut R, max_bytes: usize) -> io::Result<String>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let mut line = Vec::new();
+    let limit = (max_bytes as u64).saturating_add(2);
+
+    (&mut *reader)
+        .take(limit)
+        .read_until(b'\n', &mut line)
+        .await?;
+
+    match line.strip_suffix(b"\r\n") {
+        Some(line) => Ok(String::from_utf8_lossy(line).into_owned()),
+        None if line.ends_with(b"\n") => Err(invalid("Chunk line without a CRLF".into())),
+        None if line.len() as u64 == limit => Err(invalid("Chunk line too long".into())),
+        None => Err(io::ErrorKind::UnexpectedEof.into()),
+    }
+}
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
@@ -2,12 +2,18 @@ use std::{collections::HashMap, fmt::Display, hash::Hash};
 
 use super::StatusCode;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Headers {
-    map: HashMap<HeaderKey, String>,
+    map: HashMap<HeaderKey, Vec<String>>,
     order: Vec<HeaderKey>,
 }
 
+impl Default for Headers {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Headers {
     pub fn new() -> Headers {
         Headers {
@@ -20,22 +26,33 @@ impl Headers {
         let mut map = Headers::new();
 
         for header in headers.split("\r\n") {
-            let (key, value) = match header.split_once(':') {
-                Some(h) => h,
-                None => {
-                    eprintln!("Invalid header: {}", header);
-                    return Err(StatusCode::BadRequest);
-                }
-            };
-
-            map.insert(key, value.trim());
+            let (key, value) = header.split_once(':').ok_or(StatusCode::BadRequest)?;
+
+            // Every line is kept, since some fields, like Set-Cookie, can't
+            // be comma-joined
+            map.append(key, value.trim());
         }
 
         Ok(map)
     }
 
+    /// The first value of `key`.
     pub fn get(&self, key: impl Into<String>) -> Option<&String> {
-        self.map.get(&HeaderKey::new(key.into()))
+        self.map.get(&HeaderKey::new(key.into()))?.first()
+    }
+
+    /// Every value of `key`, in the order they were added.
+    pub fn get_all(&self, key: impl Into<String>) -> Vec<&String> {
+        self.map
+            .get(&HeaderKey::new(key.into()))
+            .map_or_else(Vec::new, |values| values.iter().collect())
+    }
+
+    /// Every value of `key` joined with commas, which is what a list field
+    /// sent over several lines means (RFC 9110 section 5.3).
+    pub fn get_list(&self, key: impl Into<String>) -> Option<String> {
+        let values = self.map.get(&HeaderKey::new(key.into()))?;
+        (!values.is_empty()).then(|| values.join(", "))
     }
 
     pub fn insert<K, V>(&mut self, key: K, value: V) -> Option<String>
@@ -49,20 +66,167 @@ impl Headers {
             self.order.push(key.clone());
         }
 
-        self.map.insert(key, value.to_string())
+        self.map
+            .insert(key, vec![value.to_string()])?
+            .into_iter()
+            .next()
+    }
+
+    /// Adds another value for `key`, sent as its own header line after any
+    /// existing ones.
+    pub fn append<K, V>(&mut self, key: K, value: V)
+    where
+        K: Into<String>,
+        V: ToString,
+    {
+        let key = HeaderKey::new(key.into());
+
+        if !self.order.contains(&key) {
+            self.order.push(key.clone());
+        }
+
+        self.map.entry(key).or_default().push(value.to_string());
+    }
+
+    /// Rewrites every header name in its canonical casing, such as
+    /// `content-type` as `Content-Type`, leaving values alone.
+    pub fn canonicalize(&mut self) {
+        for key in &mut self.order {
+            key.original = canonical_name(&key.lowercase);
+        }
+    }
+
+    /// The number of header lines, counting each value of a repeated header.
+    pub fn len(&self) -> usize {
+        self.map.values().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.order.is_empty()
+    }
+
+    /// The length of these headers as written, one `Name: value\r\n` line
+    /// per value.
+    pub fn byte_len(&self) -> usize {
+        self.order
+            .iter()
+            .flat_map(|key| self.map[key].iter().map(move |value| (key, value)))
+            .map(|(key, value)| key.original.len() + value.len() + 4)
+            .sum()
+    }
+
+    pub fn remove(&mut self, key: impl Into<String>) -> Option<String> {
+        let key = HeaderKey::new(key.into());
+
+        self.order.retain(|k| k != &key);
+        self.map.remove(&key)?.into_iter().next()
     }
 }
 
 impl Display for Headers {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         for key in &self.order {
-            write!(f, "{}: {}\r\n", key, self.map[key])?;
+            for value in &self.map[key] {
+                write!(f, "{}: {}\r\n", key, value)?;
+            }
         }
 
         Ok(())
     }
 }
 
+/// Splits a parameterized header value such as `text/html; charset=utf-8`
+/// on `;` into `(name, value)` pairs. The leading token comes first with no
+/// value, and quoted values are unquoted.
+pub fn parse_params(value: &str) -> Vec<(String, Option<String>)> {
+    split_unquoted(value, ';')
+        .into_iter()
+        .map(|param| match param.split_once('=') {
+            Some((k, v)) => (k.trim().to_string(), Some(unquote(v.trim()))),
+            None => (param.to_string(), None),
+        })
+        .collect()
+}
+
+/// Splits a comma-separated header value such as
+/// `max-age=0, must-revalidate` into its elements.
+pub fn parse_list(value: &str) -> Vec<&str> {
+    split_unquoted(value, ',')
+}
+
+fn split_unquoted(value: &str, sep: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut quoted = false;
+    let mut escaped = false;
+    let mut start = 0;
+
+    for (i, c) in value.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            c if c == sep && !quoted => {
+                parts.push(value[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+
+    parts.push(value[start..].trim());
+    parts.retain(|p| !p.is_empty());
+    parts
+}
+
+fn unquote(value: &str) -> String {
+    let inner = match value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+        Some(inner) => inner,
+        None => return value.to_string(),
+    };
+
+    let mut out = String::new();
+    let mut chars = inner.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => out.extend(chars.next()),
+            c => out.push(c),
+        }
+    }
+
+    out
+}
+
+/// Title-cases each `-` separated word of a lowercase header name, except
+/// for the names whose registered spelling isn't title case.
+fn canonical_name(lowercase: &str) -> String {
+    let special = match lowercase {
+        "etag" => "ETag",
+        "te" => "TE",
+        "dnt" => "DNT",
+        "www-authenticate" => "WWW-Authenticate",
+        "content-md5" => "Content-MD5",
+        "x-xss-protection" => "X-XSS-Protection",
+        _ => "",
+    };
+
+    if !special.is_empty() {
+        return special.to_string();
+    }
+
+    lowercase
+        .split('-')
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<String>>()
+        .join("-")
+}
+
 #[derive(Debug, Eq, Clone)]
 struct HeaderKey {
     original: String,
@@ -116,4 +280,116 @@ mod test {
         assert!(matches!(headers.get("proxy-connection"), Some(host) if host == "Keep-Alive"));
         assert_eq!(format!("{}", headers), raw.to_string() + "\r\n");
     }
+
+    #[test]
+    fn it_can_remove_headers() {
+        let mut headers = Headers::new();
+        headers.insert("Host", "example.com");
+        headers.insert("Cookie", "id=1");
+        headers.insert("Accept", "*/*");
+
+        assert!(matches!(headers.remove("COOKIE"), Some(value) if value == "id=1"));
+        assert_eq!(headers.get("cookie"), None);
+        assert_eq!(headers.remove("Cookie"), None);
+        assert_eq!(
+            format!("{}", headers),
+            "Host: example.com\r\nAccept: */*\r\n"
+        );
+    }
+
+    #[test]
+    fn it_keeps_every_set_cookie() {
+        let headers =
+            Headers::parse("Set-Cookie: a=1\r\nset-cookie: b=2\r\nServer: origin").unwrap();
+
+        assert!(matches!(headers.get("Set-Cookie"), Some(value) if value == "a=1"));
+        assert_eq!(
+            format!("{}", headers),
+            "Set-Cookie: a=1\r\nSet-Cookie: b=2\r\nServer: origin\r\n"
+        );
+    }
+
+    #[test]
+    fn it_keeps_every_repeated_header() {
+        let headers = Headers::parse("Via: 1.1 a\r\nServer: origin\r\nvia: 1.1 b").unwrap();
+
+        assert!(matches!(headers.get("Via"), Some(value) if value == "1.1 a"));
+        assert_eq!(headers.get_all("VIA"), ["1.1 a", "1.1 b"]);
+        assert_eq!(headers.get_list("Via").as_deref(), Some("1.1 a, 1.1 b"));
+        assert_eq!(headers.get_list("Host"), None);
+        assert_eq!(
+            format!("{}", headers),
+            "Via: 1.1 a\r\nVia: 1.1 b\r\nServer: origin\r\n"
+        );
+    }
+
+    #[test]
+    fn it_counts_header_lines() {
+        let mut headers = Headers::parse("Host: mattymo.dev\r\nSet-Cookie: a=1").unwrap();
+        headers.append("Set-Cookie", "b=2");
+
+        assert_eq!(headers.len(), 3);
+        assert!(!headers.is_empty());
+        assert!(Headers::new().is_empty());
+    }
+
+    #[test]
+    fn it_can_canonicalize_header_names() {
+        let mut headers = Headers::parse(
+            "content-type: text/HTML\r\netag: \"abc\"\r\nWWW-authenticate: Basic\r\nx-api-key: k",
+        )
+        .unwrap();
+
+        headers.canonicalize();
+
+        assert_eq!(
+            format!("{}", headers),
+            concat!(
+                "Content-Type: text/HTML\r\n",
+                "ETag: \"abc\"\r\n",
+                "WWW-Authenticate: Basic\r\n",
+                "X-Api-Key: k\r\n",
+            )
+        );
+    }
+
+    #[test]
+    fn it_can_parse_params() {
+        assert_eq!(
+            parse_params("text/html; charset=utf-8"),
+            vec![
+                (String::from("text/html"), None),
+                (String::from("charset"), Some(String::from("utf-8"))),
+            ]
+        );
+
+        assert_eq!(
+            parse_params("attachment; filename=\"a;b \\\"c\\\".txt\"; inline"),
+            vec![
+                (String::from("attachment"), None),
+                (
+                    String::from("filename"),
+                    Some(String::from("a;b \"c\".txt"))
+                ),
+                (String::from("inline"), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn it_can_parse_comma_separated_params() {
+        // Cache-Control separates directives with commas, not semicolons
+        let directives: Vec<_> = parse_list("max-age=0, must-revalidate")
+            .into_iter()
+            .flat_map(parse_params)
+            .collect();
+
+        assert_eq!(
+            directives,
+            vec![
+                (String::from("max-age"), Some(String::from("0"))),
+                (String::from("must-revalidate"), None),
+            ]
+        );
+    }
 }
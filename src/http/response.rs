@@ -1,19 +1,37 @@
 use std::fmt::Display;
 use std::io;
-use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
 
-use super::{Headers, StatusCode};
+use super::{
+    Headers, ParseOptions, StatusCode,
+    chunked::{is_chunked, read_chunked},
+    parse_content_length,
+};
 
+/// How many interim 1xx responses may come ahead of the final one.
+const MAX_INTERIM_RESPONSES: usize = 16;
+
+#[derive(Clone)]
 pub struct Response {
     pub version: String,
     pub status_code: StatusCode,
     pub status_message: String,
     pub headers: Headers,
-    pub body: String,
+    pub body: Vec<u8>,
 }
 
 impl Response {
     pub async fn parse<R>(readable: &mut R) -> Result<Response, io::Error>
+    where
+        R: AsyncRead + Unpin,
+    {
+        Response::parse_with(readable, &ParseOptions::default()).await
+    }
+
+    pub async fn parse_with<R>(
+        readable: &mut R,
+        options: &ParseOptions,
+    ) -> Result<Response, io::Error>
     where
         R: AsyncRead + Unpin,
     {
@@ -22,92 +40,181 @@ impl Response {
 
         let delim = "\r\n\r\n";
 
-        while !tmp.windows(delim.len()).any(|win| win == delim.as_bytes()) {
-            let n = readable.read(&mut tmp).await.unwrap_or(0);
+        let max_head_bytes = options.max_head_bytes.unwrap_or(usize::MAX);
+        let mut interim = 0;
+
+        // Interim responses, like 100 Continue or 103 Early Hints, come
+        // ahead of the final one and are skipped. A 101 is final, since
+        // what follows it isn't HTTP
+        let end = loop {
+            while !buf.windows(delim.len()).any(|win| win == delim.as_bytes()) {
+                if buf.len() > max_head_bytes {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "Response headers too large",
+                    ));
+                }
+
+                let n = readable.read(&mut tmp).await?;
 
-            if n == 0 {
-                break; // Connection closed
+                if n == 0 {
+                    break; // Connection closed
+                }
+
+                buf.extend_from_slice(&tmp[..n]);
             }
 
-            buf.extend_from_slice(&tmp[..n]);
-        }
+            let end = match buf.windows(delim.len()).position(|w| w == delim.as_bytes()) {
+                Some(end) => end,
+                None => {
+                    return Err(io::Error::other("Invalid HTTP response"));
+                }
+            };
 
-        let s = String::from_utf8_lossy(&buf);
+            let head = String::from_utf8_lossy(&buf[..end]);
+            let status_line = head.lines().next().unwrap_or_default();
+            let status = status_line
+                .split_whitespace()
+                .nth(1)
+                .and_then(|code| code.parse::<u16>().ok());
 
-        let (headers, mut body) = match s.split_once(delim) {
-            Some((h, b)) => (h, String::from(b)),
-            None => {
+            if !matches!(status, Some(100..200)) || status == Some(101) {
+                break end;
+            }
+
+            interim += 1;
+            if interim > MAX_INTERIM_RESPONSES {
                 return Err(io::Error::new(
-                    io::ErrorKind::Other,
-                    "Invalid HTTP response",
+                    io::ErrorKind::InvalidData,
+                    "Too many interim responses",
                 ));
             }
+
+            log!(options.tag => "Skipping interim response: {}", status_line);
+            buf.drain(..end + delim.len());
         };
 
+        // The head is text; the body is whatever bytes follow it
+        let mut body = buf.split_off(end + delim.len());
+        let s = String::from_utf8_lossy(&buf[..end]);
+        let headers = s.as_ref();
+
         let (mut head, headers) = match headers.split_once("\r\n") {
             Some((head, headers)) => (head.split_whitespace(), headers),
             None => {
-                return Err(io::Error::new(
-                    io::ErrorKind::Other,
-                    "Invalid HTTP headers in response",
-                ));
+                return Err(io::Error::other("Invalid HTTP headers in response"));
             }
         };
 
         let version = match head.next() {
             Some(v) => v.to_string(),
             None => {
-                return Err(io::Error::new(
-                    io::ErrorKind::Other,
-                    "Invalid HTTP version in response",
-                ));
+                return Err(io::Error::other("Invalid HTTP version in response"));
             }
         };
 
         let status_code = match head.next() {
             Some(code) => StatusCode::parse(code),
             None => {
-                return Err(io::Error::new(
-                    io::ErrorKind::Other,
-                    "Invalid HTTP status code in response",
-                ));
+                return Err(io::Error::other("Invalid HTTP status code in response"));
             }
         };
 
         let status_message = head.collect::<Vec<_>>().join(" ");
 
-        let headers = match Headers::parse(headers) {
+        let mut headers = match Headers::parse(headers) {
             Ok(h) => h,
-            Err(_) => return Err(io::Error::new(io::ErrorKind::Other, "Invalid headers")),
+            Err(_) => {
+                log!(options.tag => "Invalid headers: {:?}", headers);
+                return Err(io::Error::other("Invalid headers"));
+            }
         };
 
-        let content_length = match headers.get("Content-Length") {
-            Some(len) => match len.parse() {
-                Ok(len) => len,
-                Err(e) => {
+        let max_body_bytes = options.max_body_bytes.unwrap_or(usize::MAX);
+        let too_large = || io::Error::new(io::ErrorKind::InvalidData, "Response body too large");
+
+        // A 1xx, 204 or 304 never has a body, whatever its headers say (RFC
+        // 9112 section 6.3); after a 101 the bytes belong to the new protocol
+        let bodiless = matches!(status_code as u16, 100..200 | 204 | 304);
+
+        // Chunked framing wins over any Content-Length. The body is kept
+        // decoded, so it goes on with a length instead
+        let chunked = !bodiless
+            && headers
+                .get_list("Transfer-Encoding")
+                .is_some_and(|te| is_chunked(&te));
+
+        if chunked {
+            let mut reader = BufReader::new(body.as_slice().chain(readable));
+            let body = read_chunked(
+                &mut reader,
+                &mut headers,
+                max_body_bytes,
+                max_head_bytes,
+                &options.tag,
+            )
+            .await
+            .map_err(|e| match e.kind() {
+                io::ErrorKind::FileTooLarge => too_large(),
+                _ => e,
+            })?;
+
+            headers.remove("Transfer-Encoding");
+            headers.remove("Trailer");
+            headers.insert("Content-Length", body.len());
+
+            return Ok(Response {
+                version,
+                status_code,
+                status_message,
+                headers,
+                body,
+            });
+        }
+
+        let content_length = match headers.get_list("Content-Length") {
+            _ if bodiless => 0,
+            Some(len) => match parse_content_length(&len) {
+                Some(len) => len,
+                None => {
                     let msg = "Error parsing content length";
-                    eprintln!("{}: {}", msg, e);
-                    return Err(io::Error::new(io::ErrorKind::Other, msg));
+                    log!(options.tag => "{}: {:?}", msg, len);
+                    return Err(io::Error::other(msg));
                 }
             },
             None => usize::MAX,
         };
 
+        if content_length != usize::MAX && content_length > max_body_bytes {
+            return Err(too_large());
+        }
+
         while body.len() < content_length {
-            let n = readable.read(&mut tmp).await.unwrap_or(0);
+            if body.len() > max_body_bytes {
+                return Err(too_large());
+            }
+
+            let n = readable.read(&mut tmp).await?;
 
             if n == 0 {
                 break; // Connection closed
             }
 
-            match str::from_utf8(&tmp[..n]) {
-                Ok(s) => body.push_str(s),
-                Err(e) => {
-                    let msg = "Error parsing response body as utf-8";
-                    eprintln!("{}: {}", msg, e);
-                    return Err(io::Error::new(io::ErrorKind::Other, msg));
-                }
-            }
+            body.extend_from_slice(&tmp[..n]);
+        }
+
+        if body.len() > max_body_bytes {
+            return Err(too_large());
+        }
+
+        // Relaying a short body would pass a truncated response off as whole
+        if content_length != usize::MAX && body.len() < content_length {
+            log!(
+                options.tag => "Upstream closed mid-body: {} of {} bytes",
+                body.len(),
+                content_length
+            );
+            return Err(io::ErrorKind::UnexpectedEof.into());
         }
 
         Ok(Response {
@@ -125,30 +232,65 @@ impl Response {
             status_message: status_code.get_status_message().into(),
             status_code,
             headers: Headers::new(),
-            body: String::new(),
+            body: Vec::new(),
         }
     }
 
+    /// Changes the status, along with the reason phrase that goes with it.
+    pub fn set_status_code(&mut self, status_code: StatusCode) -> &mut Response {
+        self.status_code = status_code;
+        self.status_message = status_code.get_status_message().into();
+        self
+    }
+
     pub fn set_status_message(&mut self, status_message: String) -> &mut Response {
         self.status_message = status_message;
         self
     }
 
+    /// The length of this response as written, without formatting it.
+    pub fn byte_len(&self) -> usize {
+        let code = (self.status_code as u16).checked_ilog10().unwrap_or(0) as usize + 1;
+        let status_line = self.version.len() + code + self.status_message.len() + 4;
+
+        status_line + self.headers.byte_len() + 2 + self.body.len()
+    }
+
+    /// Writes the response with its body byte for byte, UTF-8 or not.
     pub async fn write<W>(&self, writable: &mut W) -> Result<(), tokio::io::Error>
     where
         W: AsyncWrite + Unpin,
     {
-        writable.write_all(format!("{}", self).as_bytes()).await
+        writable.write_all(&self.to_bytes()).await
+    }
+
+    pub fn write_sync<W>(&self, writable: &mut W) -> Result<(), io::Error>
+    where
+        W: io::Write,
+    {
+        io::Write::write_all(writable, &self.to_bytes())
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.head().into_bytes();
+        bytes.extend_from_slice(&self.body);
+        bytes
+    }
+
+    /// The status line and headers, through the blank line ending them.
+    fn head(&self) -> String {
+        format!(
+            "{} {} {}\r\n{}\r\n",
+            self.version, self.status_code, self.status_message, self.headers
+        )
     }
 }
 
+/// Shows the response as text, replacing any body bytes that aren't UTF-8;
+/// `write` sends them as they are.
 impl Display for Response {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{} {} {}\r\n{}\r\n{}",
-            self.version, self.status_code, self.status_message, self.headers, self.body
-        )
+        write!(f, "{}{}", self.head(), String::from_utf8_lossy(&self.body))
     }
 }
 
@@ -158,7 +300,14 @@ pub struct ResponseBuilder {
     status_code: Option<StatusCode>,
     status_message: Option<String>,
     headers: Option<Headers>,
-    body: Option<String>,
+    body: Option<Vec<u8>>,
+    chunked: bool,
+}
+
+impl Default for ResponseBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl ResponseBuilder {
@@ -169,15 +318,18 @@ impl ResponseBuilder {
             status_message: None,
             headers: None,
             body: None,
+            chunked: false,
         }
     }
 
     pub fn build(self) -> Result<Response, &'static str> {
         let status_code = self.status_code.ok_or("missing status code")?;
-        let mut headers = self.headers.unwrap_or_else(Headers::new);
-        let body = self.body.unwrap_or_else(String::new);
+        let mut headers = self.headers.unwrap_or_default();
+        let body = self.body.unwrap_or_default();
 
-        if headers.get("Content-Length") == None && body.len() != 0 {
+        if self.chunked {
+            headers.insert("Transfer-Encoding", "chunked");
+        } else if headers.get("Content-Length").is_none() && !body.is_empty() {
             headers.insert("Content-Length", body.len());
         }
 
@@ -223,18 +375,71 @@ impl ResponseBuilder {
         self
     }
 
-    pub fn add_body(mut self, body: impl Into<String>) -> Self {
+    pub fn add_body(mut self, body: impl Into<Vec<u8>>) -> Self {
         self.body = Some(body.into());
+        self.chunked = false;
+        self
+    }
+
+    /// Sends the body with `Transfer-Encoding: chunked`, one chunk per item.
+    /// Empty items are skipped since a zero-length chunk ends the body.
+    pub fn add_chunked_body<I, S>(mut self, chunks: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<Vec<u8>>,
+    {
+        let mut body = Vec::new();
+
+        for chunk in chunks.into_iter().map(Into::into) {
+            if !chunk.is_empty() {
+                body.extend_from_slice(format!("{:x}\r\n", chunk.len()).as_bytes());
+                body.extend_from_slice(&chunk);
+                body.extend_from_slice(b"\r\n");
+            }
+        }
+
+        body.extend_from_slice(b"0\r\n\r\n");
+
+        self.body = Some(body);
+        self.chunked = true;
         self
     }
 }
 
 #[cfg(test)]
 mod test {
-    use std::io::Cursor;
+    use std::{
+        io::Cursor,
+        pin::Pin,
+        task::{Context, Poll},
+        time::Duration,
+    };
+
+    use tokio::io::ReadBuf;
 
     use super::*;
 
+    /// Yields `data` once and then fails, like a connection reset mid-message.
+    struct Reset {
+        data: Option<Vec<u8>>,
+    }
+
+    impl AsyncRead for Reset {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<io::Result<()>> {
+            match self.data.take() {
+                Some(data) => {
+                    buf.put_slice(&data);
+                    Poll::Ready(Ok(()))
+                }
+                None => Poll::Ready(Err(io::ErrorKind::ConnectionReset.into())),
+            }
+        }
+    }
+
     #[tokio::test]
     async fn it_can_parse_a_response() {
         let body = "Hello, world!";
@@ -260,7 +465,7 @@ mod test {
         assert_eq!(res.status_message, "OK");
         assert!(matches!(res.headers.get("Server"), Some(s) if s == "Apache"));
         assert!(matches!(res.headers.get("Cache-Control"), Some(s) if s == "no-store"));
-        assert_eq!(res.body, body);
+        assert_eq!(res.body, body.as_bytes());
         assert_eq!(format!("{}", res), raw);
     }
 
@@ -280,6 +485,40 @@ mod test {
         assert_eq!(format!("{}", res), raw);
     }
 
+    #[tokio::test]
+    async fn it_can_parse_client_errors() {
+        for (status_code, raw) in [
+            (
+                StatusCode::PaymentRequired,
+                "HTTP/1.1 402 Payment Required\r\nContent-Length: 0\r\n\r\n",
+            ),
+            (
+                StatusCode::Forbidden,
+                "HTTP/1.1 403 Forbidden\r\nContent-Length: 0\r\n\r\n",
+            ),
+            (
+                StatusCode::NotFound,
+                "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n",
+            ),
+        ] {
+            let res = Response::parse(&mut Cursor::new(raw)).await.unwrap();
+
+            assert_eq!(res.status_code, status_code);
+            assert_eq!(format!("{}", res), raw);
+        }
+    }
+
+    #[tokio::test]
+    async fn it_tolerates_extra_whitespace_in_the_status_line() {
+        let raw = "HTTP/1.1  404\t Not  Found\r\nContent-Length: 0\r\n\r\n";
+
+        let res = Response::parse(&mut Cursor::new(raw)).await.unwrap();
+
+        assert_eq!(res.status_code, StatusCode::NotFound);
+        assert_eq!(res.status_message, "Not Found");
+        assert!(format!("{}", res).starts_with("HTTP/1.1 404 Not Found\r\n"));
+    }
+
     #[tokio::test]
     async fn it_can_parse_no_status_message() {
         let raw = concat!(
@@ -307,4 +546,364 @@ mod test {
 
         assert_eq!(format!("{}", req), raw);
     }
+
+    #[test]
+    fn it_can_write_to_a_sync_writer() {
+        let res = ResponseBuilder::new()
+            .add_status_code(StatusCode::NotFound)
+            .add_body("nope")
+            .build()
+            .unwrap();
+
+        let mut out = Vec::new();
+        res.write_sync(&mut out).unwrap();
+
+        assert_eq!(
+            out,
+            b"HTTP/1.1 404 Not Found\r\nContent-Length: 4\r\n\r\nnope"
+        );
+    }
+
+    #[tokio::test]
+    async fn it_keeps_every_set_cookie() {
+        let raw = concat!(
+            "HTTP/1.1 200 OK\r\n",
+            "Set-Cookie: a=1; Path=/\r\n",
+            "Set-Cookie: b=2; Expires=Wed, 21 Oct 2015 07:28:00 GMT\r\n",
+            "Content-Length: 0\r\n",
+            "\r\n",
+        );
+
+        let res = Response::parse(&mut Cursor::new(raw)).await.unwrap();
+
+        assert_eq!(
+            res.headers.get_all("set-cookie"),
+            ["a=1; Path=/", "b=2; Expires=Wed, 21 Oct 2015 07:28:00 GMT"]
+        );
+        assert!(res.headers.get_all("Cookie").is_empty());
+        assert_eq!(format!("{}", res), raw);
+    }
+
+    #[tokio::test]
+    async fn it_keeps_binary_bodies_intact() {
+        let mut raw = b"HTTP/1.1 200 OK\r\nContent-Length: 4\r\n\r\n".to_vec();
+        raw.extend_from_slice(&[0x89, b'P', 0x00, 0xff]);
+
+        let res = Response::parse(&mut Cursor::new(&raw)).await.unwrap();
+        assert_eq!(res.body, [0x89, b'P', 0x00, 0xff]);
+
+        let mut written = Vec::new();
+        res.write_sync(&mut written).unwrap();
+        assert_eq!(written, raw);
+    }
+
+    #[tokio::test]
+    async fn it_can_measure_a_response() {
+        let raw = concat!(
+            "HTTP/1.1 404 Not Found\r\n",
+            "Set-Cookie: a=1\r\n",
+            "Set-Cookie: b=2\r\n",
+            "Content-Length: 4\r\n",
+            "\r\n",
+            "nope",
+        );
+        let res = Response::parse(&mut Cursor::new(raw)).await.unwrap();
+
+        assert_eq!(res.byte_len(), raw.len());
+        assert_eq!(res.byte_len(), res.to_string().len());
+    }
+
+    #[test]
+    fn it_can_build_a_chunked_response() {
+        let res = ResponseBuilder::new()
+            .add_status_code(StatusCode::OK)
+            .add_header("Content-Type", "text/plain")
+            .add_chunked_body(["Hello, ", "", "world! This chunk is longer."])
+            .build()
+            .unwrap();
+
+        let expected = concat!(
+            "HTTP/1.1 200 OK\r\n",
+            "Content-Type: text/plain\r\n",
+            "Transfer-Encoding: chunked\r\n",
+            "\r\n",
+            "7\r\nHello, \r\n",
+            "1c\r\nworld! This chunk is longer.\r\n",
+            "0\r\n\r\n",
+        );
+
+        assert_eq!(format!("{}", res), expected);
+        assert_eq!(res.headers.get("Content-Length"), None);
+    }
+
+    #[tokio::test]
+    async fn it_decodes_a_chunked_body() {
+        let raw = concat!(
+            "HTTP/1.1 200 OK\r\n",
+            "Content-Type: text/plain\r\n",
+            "Transfer-Encoding: chunked\r\n",
+            "\r\n",
+            "7\r\nHello, \r\n",
+            "6\r\nworld!\r\n",
+            "0\r\n\r\n",
+        );
+
+        let res = Response::parse(&mut Cursor::new(raw)).await.unwrap();
+
+        assert_eq!(res.body, b"Hello, world!");
+        assert_eq!(res.headers.get("Transfer-Encoding"), None);
+        assert!(matches!(res.headers.get("Content-Length"), Some(len) if len == "13"));
+    }
+
+    #[tokio::test]
+    async fn it_ignores_chunk_extensions() {
+        let raw = concat!(
+            "HTTP/1.1 200 OK\r\n",
+            "Transfer-Encoding: chunked\r\n",
+            "\r\n",
+            "5;foo=bar\r\nhello\r\n",
+            "0\r\n\r\n",
+        );
+
+        let res = Response::parse(&mut Cursor::new(raw)).await.unwrap();
+
+        assert_eq!(res.body, b"hello");
+    }
+
+    #[tokio::test]
+    async fn it_keeps_trailers_from_a_chunked_body() {
+        let raw = concat!(
+            "HTTP/1.1 200 OK\r\n",
+            "Transfer-Encoding: chunked\r\n",
+            "Trailer: Checksum\r\n",
+            "\r\n",
+            "2\r\nhi\r\n",
+            "0\r\n",
+            "Checksum: abc123\r\n",
+            "\r\n",
+        );
+
+        let res = Response::parse(&mut Cursor::new(raw)).await.unwrap();
+
+        assert_eq!(res.body, b"hi");
+        assert!(matches!(res.headers.get("Checksum"), Some(sum) if sum == "abc123"));
+        assert_eq!(res.headers.get("Trailer"), None);
+    }
+
+    #[tokio::test]
+    async fn it_drops_trailers_that_would_act_as_headers() {
+        let raw = concat!(
+            "HTTP/1.1 200 OK\r\n",
+            "Transfer-Encoding: chunked\r\n",
+            "Trailer: Set-Cookie, Location, Checksum\r\n",
+            "\r\n",
+            "2\r\nhi\r\n",
+            "0\r\n",
+            "Set-Cookie: session=forged\r\n",
+            "Location: https://evil.example/\r\n",
+            "Checksum: abc123\r\n",
+            "\r\n",
+        );
+
+        let res = Response::parse(&mut Cursor::new(raw)).await.unwrap();
+
+        assert!(res.headers.get_all("Set-Cookie").is_empty());
+        assert_eq!(res.headers.get("Location"), None);
+        assert!(matches!(res.headers.get("Checksum"), Some(sum) if sum == "abc123"));
+    }
+
+    #[tokio::test]
+    async fn it_reads_no_body_for_a_204_or_304() {
+        for raw in [
+            "HTTP/1.1 204 No Content\r\nTransfer-Encoding: chunked\r\n\r\n",
+            "HTTP/1.1 304 Not Modified\r\nContent-Length: 5\r\n\r\n",
+        ] {
+            // The upstream stays open, so this only returns if parse stops
+            // at the head
+            let (mut upstream, mut client) = tokio::io::duplex(256);
+            upstream.write_all(raw.as_bytes()).await.unwrap();
+
+            let res = tokio::time::timeout(Duration::from_secs(1), Response::parse(&mut client))
+                .await
+                .unwrap()
+                .unwrap();
+
+            assert_eq!(res.body, b"");
+            assert_eq!(format!("{}", res), raw);
+        }
+    }
+
+    #[tokio::test]
+    async fn it_rejects_bad_chunk_framing() {
+        let options = ParseOptions {
+            max_body_bytes: Some(8),
+            max_head_bytes: Some(64),
+            ..Default::default()
+        };
+
+        let head = "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n";
+        let long_extension = format!("2;{}\r\nhi\r\n0\r\n\r\n", "a".repeat(100));
+
+        for body in [
+            "zz\r\nhi\r\n0\r\n\r\n",
+            "+2\r\nhi\r\n0\r\n\r\n",
+            "2\r\nhello\r\n0\r\n\r\n",
+            "9\r\n123456789\r\n0\r\n\r\n",
+            "ffffffffffffffffffff\r\n",
+            long_extension.as_str(),
+        ] {
+            let raw = format!("{}{}", head, body);
+            let err = Response::parse_with(&mut Cursor::new(raw), &options)
+                .await
+                .err()
+                .unwrap();
+
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{:?}", body);
+        }
+
+        let raw = format!("{}5\r\nhel", head);
+        let err = Response::parse(&mut Cursor::new(raw)).await.err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[tokio::test]
+    async fn it_finds_the_end_of_a_head_split_across_reads() {
+        let (_upstream, open) = tokio::io::duplex(64);
+        let mut reader = Cursor::new("HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r")
+            .chain(Cursor::new("\nhi"))
+            .chain(open);
+
+        // The upstream stays open, so this only returns if the head is found
+        let res = tokio::time::timeout(Duration::from_secs(1), Response::parse(&mut reader))
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(res.body, b"hi");
+    }
+
+    #[tokio::test]
+    async fn it_skips_interim_responses() {
+        let raw = concat!(
+            "HTTP/1.1 100 Continue\r\n\r\n",
+            "HTTP/1.1 103 Early Hints\r\nLink: </style.css>; rel=preload\r\n\r\n",
+            "HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello",
+        );
+
+        // All at once, and with the final response in a later read
+        let (_upstream, open) = tokio::io::duplex(64);
+        let mut split = Cursor::new("HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 2")
+            .chain(Cursor::new("00 OK\r\nContent-Length: 5\r\n\r\nhello"))
+            .chain(open);
+
+        for res in [
+            Response::parse(&mut Cursor::new(raw)).await.unwrap(),
+            Response::parse(&mut split).await.unwrap(),
+        ] {
+            assert_eq!(res.status_code, StatusCode::OK);
+            assert_eq!(res.headers.get("Link"), None);
+            assert_eq!(res.body, b"hello");
+        }
+
+        let endless = "HTTP/1.1 100 Continue\r\n\r\n".repeat(100);
+        assert!(Response::parse(&mut Cursor::new(endless)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn it_does_not_wait_for_a_body_after_switching_protocols() {
+        let raw_res = "HTTP/1.1 101 Switching Protocols\r\nUpgrade: h2c\r\n\r\n";
+
+        let (mut upstream, mut client) = tokio::io::duplex(64);
+        upstream.write_all(raw_res.as_bytes()).await.unwrap();
+
+        // The upstream stays open, so this only returns if parse stops at the head
+        let res = Response::parse(&mut client).await.unwrap();
+
+        assert_eq!(res.status_code, StatusCode::SwitchingProtocols);
+        assert_eq!(res.body, b"");
+    }
+
+    #[tokio::test]
+    async fn it_returns_an_error_when_reset_mid_body() {
+        let raw = "HTTP/1.1 200 OK\r\nContent-Length: 100\r\n\r\npartial";
+        let mut reader = Reset {
+            data: Some(raw.as_bytes().to_vec()),
+        };
+
+        let err = Response::parse(&mut reader).await.err().unwrap();
+
+        assert_eq!(err.kind(), io::ErrorKind::ConnectionReset);
+    }
+
+    #[tokio::test]
+    async fn it_returns_an_error_when_closed_mid_body() {
+        let raw = "HTTP/1.1 200 OK\r\nContent-Length: 100\r\n\r\npartial";
+
+        let err = Response::parse(&mut Cursor::new(raw)).await.err().unwrap();
+
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[tokio::test]
+    async fn it_returns_an_error_when_reset_mid_headers() {
+        let mut reader = Reset {
+            data: Some(b"HTTP/1.1 200 OK\r\nContent-Le".to_vec()),
+        };
+
+        let err = Response::parse(&mut reader).await.err().unwrap();
+
+        assert_eq!(err.kind(), io::ErrorKind::ConnectionReset);
+    }
+
+    #[tokio::test]
+    async fn it_rejects_headers_over_the_limit() {
+        let options = ParseOptions {
+            max_head_bytes: Some(64),
+            ..Default::default()
+        };
+
+        let raw = format!(
+            "HTTP/1.1 200 OK\r\nX-Padding: {}\r\nContent-Length: 0\r\n\r\n",
+            "a".repeat(8 * 1024)
+        );
+        let err = Response::parse_with(&mut Cursor::new(raw), &options)
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let within = "HTTP/1.1 204 No Content\r\nServer: Apache\r\n\r\n";
+        let res = Response::parse_with(&mut Cursor::new(within), &options)
+            .await
+            .unwrap();
+        assert_eq!(res.status_code, StatusCode::NoContent);
+    }
+
+    #[tokio::test]
+    async fn it_rejects_a_body_over_the_limit() {
+        let options = ParseOptions {
+            max_body_bytes: Some(8),
+            ..Default::default()
+        };
+
+        let declared = "HTTP/1.1 200 OK\r\nContent-Length: 9\r\n\r\n123456789";
+        let err = Response::parse_with(&mut Cursor::new(declared), &options)
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let undeclared = "HTTP/1.1 200 OK\r\nServer: Apache\r\n\r\n123456789";
+        let err = Response::parse_with(&mut Cursor::new(undeclared), &options)
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let within = "HTTP/1.1 200 OK\r\nContent-Length: 8\r\n\r\n12345678";
+        let res = Response::parse_with(&mut Cursor::new(within), &options)
+            .await
+            .unwrap();
+        assert_eq!(res.body, b"12345678");
+    }
 }
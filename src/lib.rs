@@ -0,0 +1,16 @@
+#![allow(clippy::upper_case_acronyms)]
+
+#[macro_use]
+pub mod log;
+
+pub mod args;
+pub mod breaker;
+pub mod cidr;
+pub mod coalesce;
+pub mod config;
+pub mod http;
+pub mod peek;
+pub mod proxy;
+pub mod sha256;
+pub mod sni;
+pub mod stats;
@@ -0,0 +1,163 @@
+//! Where rox's log lines go. Each is written to stderr as it's logged, and
+//! tests can also collect the ones logged on their thread.
+
+use std::{
+    fmt,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+#[cfg(test)]
+use std::{cell::RefCell, future::Future};
+
+/// Logs a line, taking the same arguments as `eprintln!`, optionally after a
+/// `Tag` and `=>` to say which connection it's about.
+macro_rules! log {
+    ($tag:expr => $($arg:tt)*) => {
+        $crate::log::write_tagged(&$tag, format_args!($($arg)*))
+    };
+    ($($arg:tt)*) => {
+        $crate::log::write(format_args!($($arg)*))
+    };
+}
+
+/// What a line is tagged with: the ID of the connection it's about, which
+/// goes in front as `[id]`, or nothing if it's about none.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Tag {
+    pub id: String,
+    /// Write the line as a JSON object instead, for `--json-logs`
+    pub json: bool,
+}
+
+impl Tag {
+    pub fn new(id: impl Into<String>, json: bool) -> Self {
+        Self {
+            id: id.into(),
+            json,
+        }
+    }
+}
+
+#[cfg(test)]
+thread_local! {
+    static CAPTURED: RefCell<Option<Vec<String>>> = const { RefCell::new(None) };
+}
+
+pub(crate) fn write(args: fmt::Arguments) {
+    #[cfg(test)]
+    CAPTURED.with_borrow_mut(|captured| {
+        if let Some(lines) = captured {
+            lines.push(args.to_string());
+        }
+    });
+
+    eprintln!("{}", args);
+}
+
+pub(crate) fn write_tagged(tag: &Tag, args: fmt::Arguments) {
+    if tag.json {
+        write(format_args!(
+            "{}",
+            format_json_line(SystemTime::now(), &tag.id, &args.to_string())
+        ));
+    } else if tag.id.is_empty() {
+        write(args);
+    } else {
+        write(format_args!("[{}] {}", tag.id, args));
+    }
+}
+
+/// A line as a single-line JSON object, with the ID `null` if it's about no
+/// connection in particular.
+fn format_json_line(time: SystemTime, id: &str, message: &str) -> String {
+    let time = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let id = match id {
+        "" => String::from("null"),
+        id => json_string(id),
+    };
+
+    format!(
+        "{{\"timestamp\":{}.{:03},\"level\":\"info\",\"event\":\"log\",\"id\":{},\"message\":{}}}",
+        time.as_secs(),
+        time.subsec_millis(),
+        id,
+        json_string(message)
+    )
+}
+
+/// Quotes `s` as a JSON string.
+pub(crate) fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+
+    out.push('"');
+    out
+}
+
+/// Awaits `f`, returning its output and the lines logged on this thread
+/// meanwhile, which on a current-thread runtime includes any tasks it
+/// spawned.
+#[cfg(test)]
+pub(crate) async fn capture<F: Future>(f: F) -> (F::Output, Vec<String>) {
+    CAPTURED.set(Some(Vec::new()));
+    let output = f.await;
+
+    (output, CAPTURED.take().unwrap_or_default())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn it_captures_lines_logged_while_awaiting() {
+        log!("before");
+
+        let ((), lines) = capture(async {
+            log!("during {}", 1);
+            tokio::spawn(async { log!("spawned") }).await.unwrap();
+        })
+        .await;
+
+        log!("after");
+
+        assert_eq!(lines, ["during 1", "spawned"]);
+    }
+
+    #[tokio::test]
+    async fn it_puts_the_tag_in_front() {
+        let ((), lines) = capture(async {
+            log!(Tag::new("abc-1", false) => "about {}", "abc");
+            log!(Tag::default() => "about nothing");
+        })
+        .await;
+
+        assert_eq!(lines, ["[abc-1] about abc", "about nothing"]);
+    }
+
+    #[test]
+    fn it_formats_json_lines() {
+        let time = UNIX_EPOCH + std::time::Duration::from_millis(1_755_046_574_561);
+
+        assert_eq!(
+            format_json_line(time, "abc-1", "GET / HTTP/1.1\r\nHost: \"a\""),
+            concat!(
+                "{\"timestamp\":1755046574.561,\"level\":\"info\",\"event\":\"log\",",
+                "\"id\":\"abc-1\",\"message\":\"GET / HTTP/1.1\\r\\nHost: \\\"a\\\"\"}",
+            )
+        );
+        assert!(format_json_line(time, "", "hi").contains("\"id\":null,"));
+    }
+}
@@ -1,11 +1,6 @@
 use std::env;
 
-use args::Args;
-use proxy::Proxy;
-
-mod args;
-mod http;
-mod proxy;
+use rox::{args::Args, proxy::Proxy};
 
 #[tokio::main]
 async fn main() {
@@ -25,7 +20,14 @@ async fn main() {
         return version();
     }
 
-    Proxy::new(args).run().await
+    if args.print_config {
+        return print!("{}", args);
+    }
+
+    if let Err(e) = Proxy::new(args).run().await {
+        eprintln!("🚨 Error: {} 🚨", e);
+        std::process::exit(1);
+    }
 }
 
 fn version() {
@@ -35,14 +37,65 @@ fn version() {
 fn help() {
     println!(
         "
-USAGE: rox[EXE] [OPTIONS]
+USAGE: rox[EXE] [OPTIONS] [@FILE...]
 
 OPTIONS:
     -h, --help                      Print help
     -v, --version                   Print version
+    -b, --bind <HOST>               Address to listen on, e.g. 0.0.0.0 or [::1] (alias --host) [default: localhost]
     -p, --port <PORT>               Specify port for proxy server [default: protocol convention]
+        --listen-fd <FD>            Accept on an inherited, already bound socket instead of --port (e.g. 3 under systemd)
+        --user-switch <UID:GID>     Drop to this user and group once the listener is bound, e.g. after binding port 80 as root (Unix)
     -P, --protocol <PROTOCOL>       Specify proxy protocol [default: http]
     -u, --user <username:password>  Specify username and password to add Basic auth to proxy
+        --credentials-file <PATH>   Also accept any username:password line from this file (# comments allowed)
+        --hash-scheme <SCHEME>      How --credentials-file passwords are stored (plain, sha256; sha256 also takes a salted
+                                    sha256$<salt>$<hex>, the hex digest of salt+password hashed 10000 times) [default: plain]
+        --allow-client <CIDR>       Only serve clients from this address range, e.g. 10.0.0.0/8 (repeatable)
+        --config <PATH>             Load options from a TOML file; flags take precedence
+        --print-config              Print the effective configuration and exit
+        --log-line                  Log `<time> <client> <method> <target> <status> <body bytes> <id>` for each request once answered
+        --json-logs                 Log those summaries, errors and every other line as one JSON object per line instead (implies --log-line)
+        --log-sample <RATIO>        Log only this fraction of connections verbosely; errors are always logged [default: 1]
+        --coalesce                  Share one upstream fetch between concurrent identical GETs
+        --canonical-headers         Send forwarded header names in canonical casing, e.g. Content-Type
+        --whoami                    Answer GET /whoami with the client's address as seen by rox
+        --landing                   Answer GET / with a page explaining how to use rox as a proxy
+        --cors                      Answer CORS preflights with 204 and allow any origin on forwarded responses
+        --add-date                  Add a Date header to responses rox generates itself
+        --trace-raw                 Log each request head as raw, escaped bytes before parsing it, --redact-header values masked
+        --strict                    Reject requests that are unusual but technically parseable
+        --warmup-time <SECONDS>     Answer 503 with Retry-After for this long after starting
+        --max-lifetime <SECONDS>    Close any connection after it has been open this long
+        --max-request-body-time <SECONDS> Answer 408 when a request body takes longer than this to arrive
+        --slow-connect-threshold <MS> Warn when opening a tunnel takes longer than this
+        --tcp-keepalive <SECONDS>   Enable TCP keepalive on tunnel sockets after this much idle time
+        --drain-signal <SIGNAL>     Stop accepting and exit once open connections finish (USR1, USR2, HUP) [default: USR1]
+        --follow-redirects <N>      Follow up to N upstream redirects for GET/HEAD when forwarding [default: 0]
+        --max-request-bytes <N>     Answer 413 to a request whose body exceeds N bytes, keeping the connection
+        --max-response-bytes <N>    Answer 502 when a forwarded response body exceeds N bytes
+        --max-forward-headers <N>   Answer 431 instead of forwarding a request with more than N headers
+        --peek-bytes <N>            Log the first N bytes sent each way through a tunnel as hex [default: 0]
+        --log-sni                   Log the server name from each tunnel's TLS ClientHello, without decrypting
+        --connect-message <MESSAGE> Status message sent when a tunnel opens [default: Connection Established]
+        --fallback-upstream <HOST:PORT> Proxy to CONNECT through when a tunnel's target can't be reached
+        --upstream-retries <N>      Retry CONNECTing through that proxy N times, backing off, unless it refuses with a 4xx [default: 0]
+        --breaker-threshold <N>     Answer 503 for an upstream after N connect failures in a row, for a cooldown
+        --breaker-window <SECONDS>  How close together those failures must be [default: 10]
+        --breaker-cooldown <SECONDS> How long to refuse before probing the upstream again [default: 30]
+        --request-id-header <NAME>  Header carrying each connection's ID to upstreams and in responses [default: X-Request-Id]
+        --block-status <CODE>       Mask relayed responses carrying this status (repeatable)
+        --block-status-as <CODE>    Status sent in place of a blocked response [default: 502]
+        --map-status <FROM=TO>      Relay upstream responses with status FROM as status TO, e.g. 418=200 (repeatable)
+        --echo-upstream-errors      Keep the upstream's body when --block-status masks a 5xx response
+        --forwarded-header <MODE>   Add a Forwarded header to forwarded requests (modes: rfc7239)
+        --drop-header <NAME>        Strip a header from forwarded requests and responses (repeatable)
+        --deny-user-agent <TEXT>    Answer 403 to requests whose User-Agent contains this, ignoring case (repeatable)
+        --redact-header <NAME>      Mask a header value as *** when logging (repeatable)
+                                    [default: Authorization, Proxy-Authorization, Cookie, Set-Cookie]
+
+Values may follow their flag or be joined to it, as in --port 9000 or --port=9000.
+Arguments may also be read from a file with @FILE, whitespace-separated.
 
 PROTOCOLS:
     http (default)
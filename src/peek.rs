@@ -0,0 +1,121 @@
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll, ready},
+};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use crate::log::Tag;
+
+/// Wraps a stream and logs (as hex) the first `limit` bytes read from it,
+/// passing every byte through untouched.
+pub struct Peek<S> {
+    inner: S,
+    tag: Tag,
+    label: &'static str,
+    limit: usize,
+    peeked: Vec<u8>,
+}
+
+impl<S> Peek<S> {
+    pub fn new(inner: S, tag: Tag, label: &'static str, limit: usize) -> Self {
+        Self {
+            inner,
+            tag,
+            label,
+            limit,
+            peeked: Vec::new(),
+        }
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for Peek<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let before = buf.filled().len();
+
+        ready!(Pin::new(&mut this.inner).poll_read(cx, buf))?;
+
+        let remaining = this.limit - this.peeked.len();
+        let read = &buf.filled()[before..];
+        let take = &read[..read.len().min(remaining)];
+
+        if !take.is_empty() {
+            log!(
+                this.tag => "Peeked {} bytes from {}: {}",
+                take.len(),
+                this.label,
+                hex(take)
+            );
+            this.peeked.extend_from_slice(take);
+        }
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for Peek<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, io::Error>> {
+        Pin::new(&mut self.get_mut().inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use tokio::io::AsyncReadExt;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn it_peeks_the_first_bytes_and_passes_everything_through() {
+        let payload = b"\x16\x03\x01\x02\x00hello, world";
+        let mut peek = Peek::new(Cursor::new(payload.to_vec()), Tag::default(), "client", 5);
+
+        let mut out = Vec::new();
+        peek.read_to_end(&mut out).await.unwrap();
+
+        assert_eq!(out, payload);
+        assert_eq!(peek.peeked, b"\x16\x03\x01\x02\x00");
+        assert_eq!(hex(&peek.peeked), "16 03 01 02 00");
+    }
+
+    #[tokio::test]
+    async fn it_tags_what_it_peeks() {
+        let mut peek = Peek::new(
+            Cursor::new(b"hi".to_vec()),
+            Tag::new("abc-1", false),
+            "client",
+            5,
+        );
+
+        let (_, lines) = crate::log::capture(peek.read_to_end(&mut Vec::new())).await;
+
+        assert_eq!(lines, ["[abc-1] Peeked 2 bytes from client: 68 69"]);
+    }
+}
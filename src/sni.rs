@@ -0,0 +1,139 @@
+/// Finds the server name (SNI) a TLS ClientHello asks for, without
+/// decrypting anything, since the ClientHello is sent in the clear. `bytes`
+/// is the start of the client's side of a tunnel and may end mid-hello, as
+/// long as the name made it; anything else gives `None`.
+pub fn server_name(bytes: &[u8]) -> Option<String> {
+    let mut record = Reader(bytes);
+
+    // TLS record header: handshake content type, version, length
+    if record.u8()? != 0x16 {
+        return None;
+    }
+    record.skip(2)?;
+    let mut handshake = Reader(record.up_to(2)?);
+
+    // Handshake header: ClientHello, 3-byte length
+    if handshake.u8()? != 0x01 {
+        return None;
+    }
+    let mut hello = Reader(handshake.up_to(3)?);
+
+    hello.skip(2 + 32)?; // client version and random
+    hello.vec(1)?; // session id
+    hello.vec(2)?; // cipher suites
+    hello.vec(1)?; // compression methods
+
+    let mut extensions = Reader(hello.up_to(2)?);
+
+    while !extensions.0.is_empty() {
+        let kind = extensions.uint(2)?;
+        let mut data = Reader(extensions.vec(2)?);
+
+        if kind != 0x0000 {
+            continue;
+        }
+
+        let mut names = Reader(data.vec(2)?);
+
+        while !names.0.is_empty() {
+            let name_type = names.u8()?;
+            let name = names.vec(2)?;
+
+            if name_type == 0x00 {
+                return String::from_utf8(name.to_vec()).ok();
+            }
+        }
+    }
+
+    None
+}
+
+/// Reads big-endian fields off the front of a slice.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        if self.0.len() < n {
+            return None;
+        }
+
+        let (head, rest) = self.0.split_at(n);
+        self.0 = rest;
+        Some(head)
+    }
+
+    fn skip(&mut self, n: usize) -> Option<()> {
+        self.take(n).map(|_| ())
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.take(1).map(|b| b[0])
+    }
+
+    fn uint(&mut self, n: usize) -> Option<usize> {
+        Some(
+            self.take(n)?
+                .iter()
+                .fold(0, |acc, b| (acc << 8) | *b as usize),
+        )
+    }
+
+    /// A field prefixed by its length in `n` bytes.
+    fn vec(&mut self, n: usize) -> Option<&'a [u8]> {
+        let len = self.uint(n)?;
+        self.take(len)
+    }
+
+    /// Like `vec`, but settles for what's there when the field runs past
+    /// the end, as the outer layers of a hello split across reads do.
+    fn up_to(&mut self, n: usize) -> Option<&'a [u8]> {
+        let len = self.uint(n)?.min(self.0.len());
+        self.take(len)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// A ClientHello for `mattymo.dev`, cut down to one cipher suite and a
+    /// few extensions on either side of the server name.
+    const CLIENT_HELLO: [u8; 88] = [
+        0x16, 0x03, 0x01, 0x00, 0x53, // record: handshake, TLS 1.0, 83 bytes
+        0x01, 0x00, 0x00, 0x4f, // ClientHello, 79 bytes
+        0x03, 0x03, // TLS 1.2
+        0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e,
+        0x0f, 0x10, 0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17, 0x18, 0x19, 0x1a, 0x1b, 0x1c, 0x1d,
+        0x1e, 0x1f, // random
+        0x00, // no session id
+        0x00, 0x02, 0x13, 0x01, // TLS_AES_128_GCM_SHA256
+        0x01, 0x00, // null compression
+        0x00, 0x24, // 36 bytes of extensions
+        0x00, 0x17, 0x00, 0x00, // extended master secret
+        0x00, 0x00, 0x00, 0x10, // server name, 16 bytes
+        0x00, 0x0e, 0x00, 0x00, 0x0b, b'm', b'a', b't', b't', b'y', b'm', b'o', b'.', b'd', b'e',
+        b'v', // host_name "mattymo.dev"
+        0x00, 0x2b, 0x00, 0x03, 0x02, 0x03, 0x04, // supported versions: TLS 1.3
+        0x00, 0x01, 0x00, 0x01, 0x01, // max fragment length
+    ];
+
+    #[test]
+    fn it_finds_the_server_name() {
+        assert_eq!(server_name(&CLIENT_HELLO).as_deref(), Some("mattymo.dev"));
+    }
+
+    #[test]
+    fn it_finds_the_server_name_in_a_partial_hello() {
+        assert_eq!(
+            server_name(&CLIENT_HELLO[..80]).as_deref(),
+            Some("mattymo.dev")
+        );
+    }
+
+    #[test]
+    fn it_ignores_anything_else() {
+        assert_eq!(server_name(b"GET / HTTP/1.1\r\n\r\n"), None);
+        assert_eq!(server_name(&CLIENT_HELLO[..70]), None);
+        assert_eq!(server_name(&[]), None);
+    }
+}
@@ -0,0 +1,103 @@
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+/// Running totals for the proxy, reported when it shuts down.
+pub struct Stats {
+    started: Instant,
+    connections: AtomicU64,
+    bytes: AtomicU64,
+    /// Tunnels that took longer than `--slow-connect-threshold` to open
+    slow_connects: AtomicU64,
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Stats {
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            connections: AtomicU64::new(0),
+            bytes: AtomicU64::new(0),
+            slow_connects: AtomicU64::new(0),
+        }
+    }
+
+    pub fn record_connection(&self) {
+        self.connections.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_bytes(&self, bytes: u64) {
+        self.bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub fn record_slow_connect(&self) {
+        self.slow_connects.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn connections(&self) -> u64 {
+        self.connections.load(Ordering::Relaxed)
+    }
+
+    pub fn bytes(&self) -> u64 {
+        self.bytes.load(Ordering::Relaxed)
+    }
+
+    pub fn slow_connects(&self) -> u64 {
+        self.slow_connects.load(Ordering::Relaxed)
+    }
+
+    pub fn uptime(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// A one-line summary of the run so far.
+    pub fn report(&self) -> String {
+        format_report(
+            self.connections(),
+            self.bytes(),
+            self.slow_connects(),
+            self.uptime(),
+        )
+    }
+}
+
+fn format_report(connections: u64, bytes: u64, slow_connects: u64, uptime: Duration) -> String {
+    format!(
+        "Served {} connection(s), proxied {} byte(s), {} slow CONNECT(s), up {}.{:03}s",
+        connections,
+        bytes,
+        slow_connects,
+        uptime.as_secs(),
+        uptime.subsec_millis()
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_can_report_totals() {
+        let stats = Stats::new();
+
+        stats.record_connection();
+        stats.record_connection();
+        stats.record_bytes(100);
+        stats.record_bytes(23);
+        stats.record_slow_connect();
+
+        assert_eq!(stats.connections(), 2);
+        assert_eq!(stats.bytes(), 123);
+        assert_eq!(stats.slow_connects(), 1);
+        assert_eq!(
+            format_report(2, 123, 1, Duration::from_millis(61_250)),
+            "Served 2 connection(s), proxied 123 byte(s), 1 slow CONNECT(s), up 61.250s"
+        );
+    }
+}